@@ -0,0 +1,42 @@
+use criterion::{
+    BatchSize, BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main,
+};
+use day01::{LocationLists, ParseMode};
+
+fn bench_solver(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day01");
+    group.sample_size(10);
+
+    for lines in [100_000, 1_000_000, 10_000_000] {
+        let input = day01::generate(lines, 2024);
+        group.throughput(Throughput::Elements(lines as u64));
+
+        group.bench_with_input(BenchmarkId::new("read", lines), &input, |b, input| {
+            b.iter(|| {
+                LocationLists::from_reader(black_box(input.as_bytes()), lines, ParseMode::Strict)
+                    .unwrap()
+            })
+        });
+
+        let lists = LocationLists::from_reader(input.as_bytes(), lines, ParseMode::Strict).unwrap();
+        group.bench_with_input(BenchmarkId::new("part1", lines), &lists, |b, lists| {
+            b.iter_batched(
+                || lists.clone(),
+                |mut lists| lists.total_distance(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("part2", lines), &lists, |b, lists| {
+            b.iter_batched(
+                || lists.clone(),
+                |mut lists| lists.similarity_score(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_solver);
+criterion_main!(benches);
@@ -1,60 +1,254 @@
-use std::collections::HashMap;
+pub use aoc_common::{ParseError, ParseMode, Parsed};
 
-/// Part 1: Sum absolute differences between sorted lists
-pub fn part1(input: &str) -> i32 {
-    let location_ids = parse(input);
-    let (mut lefts, mut rights): (Vec<_>, Vec<_>) = location_ids.iter().cloned().unzip();
-    lefts.sort_unstable();
-    rights.sort_unstable();
+use aoc_common::AocParse;
+use aoc_common::format::parse_lines_by;
+use aoc_common::ints::{fixed_uint, parse_int};
+use aoc_common::rng::Rng;
+use std::fmt;
+use std::fmt::Write as _;
+use std::io::{self, BufRead};
 
-    lefts
-        .iter()
-        .zip(rights.iter())
-        .map(|(l, r)| (l - r).abs())
-        .sum()
+/// Part 1: Sum absolute differences between sorted lists
+pub fn part1(input: &str) -> i64 {
+    lists_from_str(input)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .total_distance()
 }
 
-/// Part 2: Calculate weighted sum based on frequency counter
+/// Part 2: Sum left elements weighted by how often they appear in the right list
 pub fn part2(input: &str) -> i64 {
-    let location_ids = parse(input);
-    let left: Vec<i32> = location_ids.iter().map(|&(l, _)| l).collect();
-    let right: Vec<i32> = location_ids.iter().map(|&(_, r)| r).collect();
+    lists_from_str(input)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .similarity_score()
+}
 
-    // Create a frequency counter map for right elements
-    let mut counter: HashMap<i32, i32> = HashMap::new();
-    for &r in &right {
-        *counter.entry(r).or_insert(0) += 1;
-    }
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic with
+pub fn answers(input: &str) -> Result<(i64, i64), ParseError> {
+    let mut lists = lists_from_str(input)?;
+    Ok((lists.total_distance(), lists.similarity_score()))
+}
 
-    // Sum left elements multiplied by their frequency in right
-    left.iter().fold(0i64, |acc, &l| {
-        let count = *counter.get(&l).unwrap_or(&0);
-        acc + (l as i64 * count as i64)
+/// Read both lists from a string, pre-sizing them from the number of lines
+fn lists_from_str(input: &str) -> Result<LocationLists, ParseError> {
+    let capacity = input.bytes().filter(|&b| b == b'\n').count() + 1;
+    LocationLists::from_reader(input.as_bytes(), capacity, ParseMode::Strict).map_err(|err| {
+        match err {
+            ReadError::Parse(err) => err,
+            // Reading from memory cannot fail
+            ReadError::Io(err) => unreachable!("{}", err),
+        }
     })
 }
 
-/// Parse function to convert string input to a vector of integer pairs
-pub fn parse(input: &str) -> Vec<(i32, i32)> {
-    input
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 2 {
-                return None;
+/// The two columns of location IDs, stored separately
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocationLists {
+    pub left: Vec<i32>,
+    pub right: Vec<i32>,
+    /// Lines skipped in lenient mode
+    pub skipped: usize,
+}
+
+impl LocationLists {
+    /// Empty lists with room for `capacity` pairs each
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            left: Vec::with_capacity(capacity),
+            right: Vec::with_capacity(capacity),
+            skipped: 0,
+        }
+    }
+
+    /// Read pairs from any buffered reader in a single pass, reusing one line buffer
+    pub fn from_reader<R: BufRead>(
+        mut reader: R,
+        capacity: usize,
+        mode: ParseMode,
+    ) -> Result<Self, ReadError> {
+        let mut lists = Self::with_capacity(capacity);
+        let mut line = String::new();
+        let mut line_index = 0;
+        let mut offset = 0;
+
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
             }
 
-            let first = parts[0].parse::<i32>().ok()?;
-            let second = parts[1].parse::<i32>().ok()?;
+            if !line.trim().is_empty() {
+                match (parse_pair(&line), mode) {
+                    (Ok(pair), _) => {
+                        lists.left.push(pair.left);
+                        lists.right.push(pair.right);
+                    }
+                    (Err(_), ParseMode::Lenient) => lists.skipped += 1,
+                    (Err(err), ParseMode::Strict) => {
+                        return Err(err.offset_by(line_index, offset).into());
+                    }
+                }
+            }
+
+            line_index += 1;
+            offset += read;
+        }
+
+        Ok(lists)
+    }
+
+    /// Sort both lists in place
+    fn sort(&mut self) {
+        self.left.sort_unstable();
+        self.right.sort_unstable();
+    }
+
+    /// Sum of distances between the lists paired up in sorted order
+    pub fn total_distance(&mut self) -> i64 {
+        self.sort();
+        self.left
+            .iter()
+            .zip(&self.right)
+            .map(|(&l, &r)| (i64::from(l) - i64::from(r)).abs())
+            .sum()
+    }
+
+    /// Sum of each left value times its number of occurrences in the right list,
+    /// counted by merging the two sorted lists run by run
+    pub fn similarity_score(&mut self) -> i64 {
+        self.sort();
+
+        let (left, right) = (&self.left, &self.right);
+        let (mut i, mut j) = (0, 0);
+        let mut score = 0i64;
+
+        while i < left.len() && j < right.len() {
+            if left[i] < right[j] {
+                i += 1;
+            } else if left[i] > right[j] {
+                j += 1;
+            } else {
+                let value = left[i];
+                let left_run = left[i..].iter().take_while(|&&l| l == value).count();
+                let right_run = right[j..].iter().take_while(|&&r| r == value).count();
+                score += i64::from(value) * left_run as i64 * right_run as i64;
+                i += left_run;
+                j += right_run;
+            }
+        }
+
+        score
+    }
+}
+
+/// Failure while reading lists from a reader
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "failed to read input: {}", err),
+            ReadError::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(err: ParseError) -> Self {
+        ReadError::Parse(err)
+    }
+}
+
+/// One line of the input: a location ID from each list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AocParse)]
+#[aoc(format = "{left: label='location ID'} {right: label='location ID'}")]
+struct LocationPair {
+    left: i32,
+    right: i32,
+}
+
+/// Parse one line, reading well-formed pairs directly and leaving anything else to
+/// the derived parser, which also explains errors
+fn parse_pair(line: &str) -> Result<LocationPair, ParseError> {
+    quick_pair(line).map_or_else(|| line.parse(), Ok)
+}
+
+/// Parse a well-formed line without building errors, or None to leave it to the derived parser
+fn quick_pair(line: &str) -> Option<LocationPair> {
+    // Real inputs are two five-digit IDs three spaces apart
+    let bytes = line.trim_end().as_bytes();
+    if bytes.len() == 13 && bytes[5..8] == *b"   " {
+        let left = fixed_uint::<i32, 5>(bytes[..5].try_into().unwrap());
+        let right = fixed_uint::<i32, 5>(bytes[8..].try_into().unwrap());
+        if let (Some(left), Some(right)) = (left, right) {
+            return Some(LocationPair { left, right });
+        }
+    }
+
+    let (left, right) = line.trim().split_once(' ')?;
+    Some(LocationPair {
+        left: parse_int(left)?,
+        right: parse_int(right.trim_start())?,
+    })
+}
+
+/// Parse the input into integer pairs, handling malformed lines according to `mode`
+pub fn parse_with(input: &str, mode: ParseMode) -> Result<Parsed<(i32, i32)>, ParseError> {
+    let parsed = parse_lines_by(input, mode, parse_pair)?;
+    Ok(Parsed {
+        items: parsed
+            .items
+            .iter()
+            .map(|pair| (pair.left, pair.right))
+            .collect(),
+        skipped: parsed.skipped,
+    })
+}
+
+/// Parse function to convert string input to a vector of integer pairs,
+/// rejecting any malformed line
+pub fn parse(input: &str) -> Result<Vec<(i32, i32)>, ParseError> {
+    parse_with(input, ParseMode::Strict).map(|parsed| parsed.items)
+}
+
+/// Generate an input of `lines` pairs of five-digit location IDs. About a quarter of the
+/// right-hand IDs repeat an earlier left-hand one so that part 2 has something to count.
+pub fn generate(lines: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut left = Vec::with_capacity(lines);
+    let mut input = String::with_capacity(lines * 14);
+
+    for _ in 0..lines {
+        let id = rng.range(10_000..100_000);
+        left.push(id);
+        let other = if rng.chance(0.25) {
+            *rng.choose(&left)
+        } else {
+            rng.range(10_000..100_000)
+        };
+        writeln!(input, "{}   {}", id, other).unwrap();
+    }
 
-            Some((first, second))
-        })
-        .collect()
+    input
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_common::metamorphic::{self, Transform};
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "3   4
  4   3
@@ -72,4 +266,139 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 31);
     }
+
+    #[test]
+    fn test_strict_parse_errors() {
+        let err = parse("1 2\n3 x").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 3, 6..7));
+        assert_eq!(err.message, "invalid location ID 'x'");
+
+        let err = parse("1 2\n\n3").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (3, 2, 6..6));
+        assert_eq!(err.message, "missing location ID");
+
+        let err = parse("1 2 3").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (1, 5, 4..5));
+        assert_eq!(
+            err.to_string(),
+            "line 1, column 5: unexpected trailing text '3'"
+        );
+    }
+
+    #[test]
+    fn test_lenient_parse() {
+        let parsed = parse_with("1 2\n3 x\n4\n5 6 7\n8 9", ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.items, vec![(1, 2), (8, 9)]);
+        assert_eq!(parsed.skipped, 3);
+    }
+
+    #[test]
+    fn test_reader_matches_parse() {
+        let lists =
+            LocationLists::from_reader("1 2\n\n3 x\n4 5\n".as_bytes(), 0, ParseMode::Lenient)
+                .unwrap();
+        assert_eq!(lists.left, vec![1, 4]);
+        assert_eq!(lists.right, vec![2, 5]);
+        assert_eq!(lists.skipped, 1);
+
+        let err =
+            LocationLists::from_reader("1 2\n\n3 x".as_bytes(), 0, ParseMode::Strict).unwrap_err();
+        assert_eq!(err.to_string(), "line 3, column 3: invalid location ID 'x'");
+        match err {
+            ReadError::Parse(err) => assert_eq!(err.span, 7..8),
+            ReadError::Io(err) => panic!("unexpected I/O error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_fixed_width_lines_match_derived_parser() {
+        for line in [
+            "38665   13337",
+            "3   4",
+            "12 345",
+            "-3   4",
+            "00042   00007 ",
+            "2147483648   2147483647",
+            "123   45x",
+            "38665\t13337",
+            "3866a   13337",
+        ] {
+            assert_eq!(parse_pair(line), line.parse::<LocationPair>(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_large_values_do_not_overflow() {
+        let input = format!("{} {}\n{} {}", i32::MIN, i32::MAX, i32::MAX, i32::MAX);
+        assert_eq!(part1(&input), i64::from(i32::MAX) - i64::from(i32::MIN));
+        assert_eq!(part2(&input), 2 * i64::from(i32::MAX));
+    }
+
+    /// Naive reference: pair the lists by repeatedly removing their smallest values,
+    /// and count each left value's occurrences with a linear scan
+    fn naive(pairs: &[(i32, i32)]) -> (i64, i64) {
+        let mut left: Vec<i32> = pairs.iter().map(|&(l, _)| l).collect();
+        let mut right: Vec<i32> = pairs.iter().map(|&(_, r)| r).collect();
+        let mut distance = 0;
+        while let Some(l) = left.iter().copied().min() {
+            let r = right.iter().copied().min().unwrap();
+            left.remove(left.iter().position(|&x| x == l).unwrap());
+            right.remove(right.iter().position(|&x| x == r).unwrap());
+            distance += (i64::from(l) - i64::from(r)).abs();
+        }
+
+        let similarity = pairs
+            .iter()
+            .map(|&(l, _)| i64::from(l) * pairs.iter().filter(|&&(_, r)| r == l).count() as i64)
+            .sum();
+        (distance, similarity)
+    }
+
+    #[test]
+    fn test_metamorphic_example() {
+        // The order of the location pairs doesn't matter
+        let transforms = metamorphic::reseeded(Transform::ShuffleLines, 0, 4);
+        metamorphic::check_both(part1, part2, EXAMPLE_INPUT, &transforms).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn parts_match_naive(
+            // Small values repeat often; five-digit ones take the fixed-width path
+            pairs in prop::collection::vec(
+                (prop_oneof![0i32..20, 10_000i32..100_000], prop_oneof![0i32..20, 10_000i32..100_000]),
+                0..60,
+            ),
+            gap in 1usize..5,
+        ) {
+            let input: String = pairs
+                .iter()
+                .map(|(l, r)| format!("{}{}{}\n", l, " ".repeat(gap), r))
+                .collect();
+            prop_assert_eq!((part1(&input), part2(&input)), naive(&pairs));
+        }
+
+        #[test]
+        fn quick_pair_agrees_with_derived(line in "[-+0-9 x]{0,14}") {
+            if let Some(pair) = quick_pair(&line) {
+                prop_assert_eq!(line.parse::<LocationPair>(), Ok(pair));
+            }
+        }
+
+        #[test]
+        fn generated_inputs_match_naive(lines in 0usize..80, seed in any::<u64>()) {
+            let input = generate(lines, seed);
+            let pairs = parse(&input).unwrap();
+            prop_assert_eq!(pairs.len(), lines);
+            prop_assert_eq!((part1(&input), part2(&input)), naive(&pairs));
+        }
+
+        #[test]
+        fn generated_inputs_are_invariant(lines in 0usize..60, seed in any::<u64>()) {
+            let transforms = metamorphic::reseeded(Transform::ShuffleLines, seed, 4);
+            let input = generate(lines, seed);
+            let checked = metamorphic::check_both(part1, part2, &input, &transforms);
+            prop_assert_eq!(checked, Ok(()));
+        }
+    }
 }
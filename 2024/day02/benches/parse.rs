@@ -0,0 +1,35 @@
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::path::Path;
+
+/// The real puzzle input when it has been downloaded, else 1000 generated reports
+fn load_input() -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../inputs/2024/day02.txt");
+    std::fs::read_to_string(path).unwrap_or_else(|_| day02::generate(1000, 2024))
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let input = load_input();
+    let mut group = c.benchmark_group("day02_parse");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    // The previous approach: str::parse on every whitespace-separated token
+    group.bench_function("str_parse", |b| {
+        b.iter(|| {
+            black_box(&input)
+                .lines()
+                .map(|line| {
+                    line.split_whitespace()
+                        .map(|token| token.parse::<i32>().unwrap())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| day02::parse(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -1,107 +1,355 @@
-/// Checks if a report is safe, according to the safety criteria,
-/// Optimized version that doesn't create intermediate collections
-pub fn is_safe(report: &[i32]) -> bool {
-    if report.len() < 2 {
-        return false; // Not enough elements to form pairs
-    }
+pub use aoc_common::{ParseError, ParseMode, Parsed};
+
+use aoc_common::AocParse;
+use aoc_common::format::parse_lines_by;
+use aoc_common::ints::split_ints;
+use aoc_common::rng::Rng;
+use std::fmt;
+
+/// Which direction the levels of a report must move in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monotonicity {
+    /// Every step must go up
+    Increasing,
+    /// Every step must go down
+    Decreasing,
+    /// All steps go up, or all steps go down
+    Either,
+}
+
+/// Parameters of the safety check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyRules {
+    /// Smallest allowed absolute difference between adjacent levels
+    pub min_step: i32,
+    /// Largest allowed absolute difference between adjacent levels
+    pub max_step: i32,
+    /// Direction the levels must move in
+    pub monotonicity: Monotonicity,
+    /// Number of levels the problem dampener may remove
+    pub max_removals: usize,
+}
 
-    // Check the first pair to determine if we're looking for increasing or decreasing
-    let first_increasing = report[0] > report[1];
+impl SafetyRules {
+    /// Rules for part 1: steps of 1 to 3 in a single direction, no removals
+    pub const PART1: SafetyRules = SafetyRules {
+        min_step: 1,
+        max_step: 3,
+        monotonicity: Monotonicity::Either,
+        max_removals: 0,
+    };
 
-    // Initialize to match the expected pattern (all increasing or all decreasing)
-    let mut valid_direction = true;
+    /// Rules for part 2: as part 1, but the dampener may remove one level
+    pub const PART2: SafetyRules = SafetyRules {
+        max_removals: 1,
+        ..SafetyRules::PART1
+    };
+
+    /// Check whether stepping from `prev` to `next` is allowed when moving in `direction`
+    fn step_allowed(&self, prev: i32, next: i32, direction: Monotonicity) -> bool {
+        let step = match direction {
+            Monotonicity::Decreasing => i64::from(prev) - i64::from(next),
+            _ => i64::from(next) - i64::from(prev),
+        };
+        (i64::from(self.min_step)..=i64::from(self.max_step)).contains(&step)
+    }
+}
+
+/// Checks if a report is safe, according to the part 1 safety criteria
+pub fn is_safe(report: &[i32]) -> bool {
+    is_safe_with(report, &SafetyRules::PART1)
+}
 
-    // Check all pairs in one pass
-    for i in 0..report.len() - 1 {
-        let curr = report[i];
-        let next = report[i + 1];
+/// Checks if a report can be made safe by removing at most `rules.max_removals` levels.
+/// At least two levels must remain. Runs in O(n * k^2) for n levels and k removals.
+pub fn is_safe_with(report: &[i32], rules: &SafetyRules) -> bool {
+    match rules.monotonicity {
+        Monotonicity::Either => {
+            safe_in_direction(report, rules, Monotonicity::Increasing)
+                || safe_in_direction(report, rules, Monotonicity::Decreasing)
+        }
+        direction => safe_in_direction(report, rules, direction),
+    }
+}
 
-        let is_curr_pair_increasing = curr > next;
+/// Dynamic programme over kept levels for a single direction.
+/// `reach[i][r]` is the number of levels kept (capped at 2) in the best valid
+/// subsequence that keeps level `i` as its last element using exactly `r` removals.
+fn safe_in_direction(report: &[i32], rules: &SafetyRules, direction: Monotonicity) -> bool {
+    let n = report.len();
+    // Removing more levels than the report has is the same as removing all of them
+    let k = rules.max_removals.min(n);
+    let mut reach = vec![vec![0u8; k + 1]; n];
 
-        // Direction check - all must be increasing or all decreasing
-        if is_curr_pair_increasing != first_increasing {
-            valid_direction = false;
-            break;
+    for i in 0..n {
+        // Level i is the first one kept, after removing everything before it
+        if i <= k {
+            reach[i][i] = 1;
         }
 
-        // Difference check - must be between 1 and 3 inclusive
-        let diff = (curr - next).abs();
-        if !(1..=3).contains(&diff) {
-            return false;
+        // Level i follows level j, with the levels between them removed
+        for j in i.saturating_sub(k + 1)..i {
+            let skipped = i - j - 1;
+            if !rules.step_allowed(report[j], report[i], direction) {
+                continue;
+            }
+            for r in 0..=k - skipped {
+                if reach[j][r] > 0 {
+                    let kept = &mut reach[i][r + skipped];
+                    *kept = (*kept).max(2);
+                }
+            }
         }
     }
 
-    valid_direction
+    // Remove everything after the last kept level
+    (n.saturating_sub(k + 1)..n).any(|i| {
+        let trailing = n - 1 - i;
+        (0..=k - trailing).any(|r| reach[i][r] >= 2)
+    })
+}
+
+/// Why a pair of adjacent levels is unsafe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The levels move against the direction of the report
+    WrongDirection,
+    /// The levels differ by less than the minimum step
+    StepTooSmall,
+    /// The levels differ by more than the maximum step
+    StepTooLarge,
 }
 
-/// Check if removing one element can make the report safe
-/// Uses a more efficient approach by avoiding repeated allocations
-fn can_be_made_safe(report: &[i32]) -> bool {
-    if report.len() < 3 {
-        return false; // Need at least 3 elements to remove 1 and have a valid pair
+/// The first unsafe pair of adjacent levels in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// Index of the first level of the pair
+    pub index: usize,
+    /// The two levels
+    pub pair: (i32, i32),
+    pub reason: Reason,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            Reason::WrongDirection => "wrong direction",
+            Reason::StepTooSmall => "step too small",
+            Reason::StepTooLarge => "step too large",
+        };
+        write!(
+            f,
+            "levels {} and {} ({} -> {}): {}",
+            self.index,
+            self.index + 1,
+            self.pair.0,
+            self.pair.1,
+            reason
+        )
     }
+}
 
-    for skip_idx in 0..report.len() {
-        // Create a temporary buffer to check safety without allocating multiple times
-        let mut temp_report = Vec::with_capacity(report.len() - 1);
+/// Safety analysis of a single report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportDiagnostic {
+    /// Whether the report is safe without any removals
+    pub safe: bool,
+    /// First unsafe pair, if the report is not safe
+    pub violation: Option<Violation>,
+    /// Indices whose removal alone makes the report safe
+    pub fixing_indices: Vec<usize>,
+}
 
-        for (i, &val) in report.iter().enumerate() {
-            if i != skip_idx {
-                temp_report.push(val);
-            }
+/// Find the first unsafe pair of levels, taking the direction from the first non-zero step
+fn first_violation(report: &[i32], rules: &SafetyRules) -> Option<Violation> {
+    let mut direction = match rules.monotonicity {
+        Monotonicity::Increasing => Some(1),
+        Monotonicity::Decreasing => Some(-1),
+        Monotonicity::Either => None,
+    };
+
+    report.windows(2).enumerate().find_map(|(index, pair)| {
+        let step = i64::from(pair[1]) - i64::from(pair[0]);
+        let sign = step.signum();
+        let violation = |reason| {
+            Some(Violation {
+                index,
+                pair: (pair[0], pair[1]),
+                reason,
+            })
+        };
+
+        if sign != 0 && *direction.get_or_insert(sign) != sign {
+            violation(Reason::WrongDirection)
+        } else if step.abs() < i64::from(rules.min_step) {
+            violation(Reason::StepTooSmall)
+        } else if step.abs() > i64::from(rules.max_step) {
+            violation(Reason::StepTooLarge)
+        } else {
+            None
         }
+    })
+}
+
+/// Indices that can each be removed on their own to make the report safe.
+/// Uses prefix and suffix validity so the whole check is linear.
+fn fixing_indices(report: &[i32], rules: &SafetyRules) -> Vec<usize> {
+    let n = report.len();
+    if n < 3 {
+        return Vec::new();
+    }
 
-        if is_safe(&temp_report) {
-            return true;
+    let directions: &[Monotonicity] = match rules.monotonicity {
+        Monotonicity::Either => &[Monotonicity::Increasing, Monotonicity::Decreasing],
+        Monotonicity::Increasing => &[Monotonicity::Increasing],
+        Monotonicity::Decreasing => &[Monotonicity::Decreasing],
+    };
+    let mut fixes = vec![false; n];
+
+    for &direction in directions {
+        let ok = |i: usize, j: usize| rules.step_allowed(report[i], report[j], direction);
+
+        // prefix[i]: levels 0..=i are safe; suffix[i]: levels i.. are safe
+        let mut prefix = vec![true; n];
+        let mut suffix = vec![true; n];
+        for i in 1..n {
+            prefix[i] = prefix[i - 1] && ok(i - 1, i);
+        }
+        for i in (0..n - 1).rev() {
+            suffix[i] = suffix[i + 1] && ok(i, i + 1);
+        }
+
+        fixes[0] |= suffix[1];
+        fixes[n - 1] |= prefix[n - 2];
+        for i in 1..n - 1 {
+            fixes[i] |= prefix[i - 1] && suffix[i + 1] && ok(i - 1, i + 1);
         }
     }
 
-    false
+    (0..n).filter(|&i| fixes[i]).collect()
+}
+
+/// Explain whether a report is safe, why not, and which single removals would fix it
+pub fn diagnose_report(report: &[i32], rules: &SafetyRules) -> ReportDiagnostic {
+    let violation = first_violation(report, rules);
+
+    ReportDiagnostic {
+        safe: report.len() >= 2 && violation.is_none(),
+        violation,
+        fixing_indices: fixing_indices(report, rules),
+    }
+}
+
+/// Diagnose every report in the input using the given rules
+pub fn diagnose(input: &str, rules: &SafetyRules) -> Result<Vec<ReportDiagnostic>, ParseError> {
+    Ok(parse(input)?
+        .iter()
+        .map(|report| diagnose_report(report, rules))
+        .collect())
+}
+
+/// Count the reports that are safe under `rules`
+pub fn count_safe(reports: &[Vec<i32>], rules: &SafetyRules) -> usize {
+    reports
+        .iter()
+        .filter(|report| is_safe_with(report, rules))
+        .count()
 }
 
 /// Part 1: Count the number of safe reports
 pub fn part1(input: &str) -> usize {
-    let reports = parse(input);
-    reports.iter().filter(|report| is_safe(report)).count()
+    let reports = parse(input).unwrap_or_else(|err| panic!("{}", err));
+    count_safe(&reports, &SafetyRules::PART1)
 }
 
 /// Part 2: Count reports that can become safe by removing one element
 pub fn part2(input: &str) -> usize {
-    let reports = parse(input);
-    reports
-        .iter()
-        .filter(|report| can_be_made_safe(report))
-        .count()
+    let reports = parse(input).unwrap_or_else(|err| panic!("{}", err));
+    count_safe(&reports, &SafetyRules::PART2)
+}
+
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic with
+pub fn answers(input: &str) -> Result<(usize, usize), ParseError> {
+    let reports = parse(input)?;
+    Ok((
+        count_safe(&reports, &SafetyRules::PART1),
+        count_safe(&reports, &SafetyRules::PART2),
+    ))
 }
 
-/// Parse function to convert string input to a vector of integer vectors
-/// Uses pre-allocation for better performance
-pub fn parse(input: &str) -> Vec<Vec<i32>> {
-    // Pre-count the number of lines for capacity planning
-    let line_count = input.lines().filter(|line| !line.trim().is_empty()).count();
-    let mut result = Vec::with_capacity(line_count);
+/// One line of the input: the levels of a report
+#[derive(Debug, Clone, PartialEq, Eq, AocParse)]
+#[aoc(format = "{levels: sep=' ', label='level'}")]
+struct Report {
+    levels: Vec<i32>,
+}
 
-    for line in input.lines().filter(|line| !line.trim().is_empty()) {
-        // Estimate the number of numbers in this line based on whitespace
-        let num_count = line.split_whitespace().count();
-        let mut nums = Vec::with_capacity(num_count);
+/// Parse one line, falling back to the derived parser to explain a malformed one
+fn parse_report(line: &str) -> Result<Report, ParseError> {
+    match split_ints(line, b' ') {
+        Some(levels) => Ok(Report { levels }),
+        None => line.parse(),
+    }
+}
 
-        for s in line.split_whitespace() {
-            // Use unwrap_or for safety but maintain performance
-            if let Ok(num) = s.parse::<i32>() {
-                nums.push(num);
-            }
+/// Parse the input into reports, handling malformed lines according to `mode`
+pub fn parse_with(input: &str, mode: ParseMode) -> Result<Parsed<Vec<i32>>, ParseError> {
+    let parsed = parse_lines_by(input, mode, parse_report)?;
+    Ok(Parsed {
+        items: parsed
+            .items
+            .into_iter()
+            .map(|report| report.levels)
+            .collect(),
+        skipped: parsed.skipped,
+    })
+}
+
+/// Parse function to convert string input to a vector of integer vectors,
+/// rejecting any malformed line
+pub fn parse(input: &str) -> Result<Vec<Vec<i32>>, ParseError> {
+    parse_with(input, ParseMode::Strict).map(|parsed| parsed.items)
+}
+
+/// Generate `reports` near-monotonic reports of 5 to 8 levels. Half are safe, and most
+/// of the rest have a single bad level that the Problem Dampener can remove.
+pub fn generate(reports: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut input = String::new();
+
+    for _ in 0..reports {
+        let direction = if rng.chance(0.5) { 1 } else { -1 };
+        let mut level = rng.range(30..70) as i64;
+        let mut levels = vec![level];
+        for _ in 1..rng.range(5..9) {
+            level += direction * rng.range(1..4) as i64;
+            levels.push(level);
         }
 
-        result.push(nums);
+        let faults = match rng.range(0..10) {
+            0..=4 => 0,
+            5..=8 => 1,
+            _ => 2,
+        };
+        for _ in 0..faults {
+            // A repeated level, a jump that is too large, or a step the wrong way
+            let at = rng.index(levels.len());
+            let bad = levels[at] + *rng.choose(&[0, 5, -5, -2 * direction]);
+            levels.insert(at + 1, bad);
+        }
+
+        let line: Vec<String> = levels.iter().map(i64::to_string).collect();
+        input.push_str(&line.join(" "));
+        input.push('\n');
     }
 
-    result
+    input
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "7 6 4 2 1
 1 2 7 8 9
@@ -119,4 +367,205 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 4);
     }
+
+    /// Brute-force reference: try removing every subset of at most `max_removals` levels
+    fn brute_force(report: &[i32], rules: &SafetyRules) -> bool {
+        let directions = match rules.monotonicity {
+            Monotonicity::Either => vec![Monotonicity::Increasing, Monotonicity::Decreasing],
+            direction => vec![direction],
+        };
+        let n = report.len();
+
+        (0u32..1 << n).any(|removed| {
+            if removed.count_ones() as usize > rules.max_removals {
+                return false;
+            }
+            let kept: Vec<i32> = (0..n)
+                .filter(|i| removed & (1 << i) == 0)
+                .map(|i| report[i])
+                .collect();
+
+            kept.len() >= 2
+                && directions.iter().any(|&direction| {
+                    kept.windows(2)
+                        .all(|pair| rules.step_allowed(pair[0], pair[1], direction))
+                })
+        })
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = SafetyRules {
+            min_step: 0,
+            max_step: 5,
+            monotonicity: Monotonicity::Increasing,
+            max_removals: 2,
+        };
+        assert!(is_safe_with(&[1, 1, 6, 20, 7, 30, 8], &rules));
+        assert!(!is_safe_with(&[1, 1, 6, 20, 7, 30, 40, 8], &rules));
+        assert!(!is_safe_with(&[9, 7, 5], &rules));
+        let two_removals = SafetyRules {
+            max_removals: 2,
+            ..SafetyRules::PART1
+        };
+        assert!(is_safe_with(&[1, 2, 3, 10, 11], &two_removals));
+        assert!(!is_safe_with(&[5], &SafetyRules::PART2));
+
+        let unlimited = SafetyRules {
+            max_removals: usize::MAX,
+            ..SafetyRules::PART1
+        };
+        assert!(is_safe_with(&[1, 9, 2, 40, 3], &unlimited));
+        assert!(!is_safe_with(&[1, 9, 40], &unlimited));
+        assert!(!is_safe_with(&[], &unlimited));
+    }
+
+    fn rules_strategy() -> impl Strategy<Value = SafetyRules> {
+        (
+            0i32..3,
+            0i32..4,
+            prop::sample::select(vec![
+                Monotonicity::Increasing,
+                Monotonicity::Decreasing,
+                Monotonicity::Either,
+            ]),
+            0usize..4,
+        )
+            .prop_map(
+                |(min_step, extra, monotonicity, max_removals)| SafetyRules {
+                    min_step,
+                    max_step: min_step + extra,
+                    monotonicity,
+                    max_removals,
+                },
+            )
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn diagnostics_agree_with_safety_check(
+            report in prop::collection::vec(1i32..12, 0..10),
+            rules in rules_strategy(),
+        ) {
+            let strict = SafetyRules { max_removals: 0, ..rules };
+            let diagnostic = diagnose_report(&report, &strict);
+            prop_assert_eq!(diagnostic.safe, is_safe_with(&report, &strict));
+
+            for i in 0..report.len() {
+                let mut removed = report.clone();
+                removed.remove(i);
+                prop_assert_eq!(
+                    diagnostic.fixing_indices.contains(&i),
+                    is_safe_with(&removed, &strict)
+                );
+            }
+        }
+
+        #[test]
+        fn matches_brute_force(
+            report in prop::collection::vec(1i32..12, 0..10),
+            rules in rules_strategy(),
+        ) {
+            prop_assert_eq!(is_safe_with(&report, &rules), brute_force(&report, &rules));
+        }
+
+        #[test]
+        fn near_monotonic_reports_match_brute_force(
+            start in 1i32..50,
+            steps in prop::collection::vec(-4i32..=4, 1..9),
+        ) {
+            let report: Vec<i32> = steps
+                .iter()
+                .scan(start, |level, step| {
+                    *level += step;
+                    Some(*level)
+                })
+                .collect();
+            prop_assert_eq!(
+                is_safe_with(&report, &SafetyRules::PART2),
+                brute_force(&report, &SafetyRules::PART2)
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        let err = parse("1 2 3\n4 five 6").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 3, 8..12));
+        assert_eq!(err.message, "invalid level 'five'");
+
+        let parsed = parse_with("1 2 3\n4 five 6\n\n7 8", ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.items, vec![vec![1, 2, 3], vec![7, 8]]);
+        assert_eq!(parsed.skipped, 1);
+    }
+
+    #[test]
+    fn test_diagnose_example() {
+        let diagnostics = diagnose(EXAMPLE_INPUT, &SafetyRules::PART1).unwrap();
+        let safe: Vec<bool> = diagnostics.iter().map(|d| d.safe).collect();
+        assert_eq!(safe, vec![true, false, false, false, false, true]);
+
+        let reasons: Vec<Option<Reason>> = diagnostics
+            .iter()
+            .map(|d| d.violation.map(|v| v.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                None,
+                Some(Reason::StepTooLarge),
+                Some(Reason::StepTooLarge),
+                Some(Reason::WrongDirection),
+                Some(Reason::StepTooSmall),
+                None,
+            ]
+        );
+
+        assert_eq!(
+            diagnostics[1].violation.unwrap().to_string(),
+            "levels 1 and 2 (2 -> 7): step too large"
+        );
+        assert!(diagnostics[1].fixing_indices.is_empty());
+        assert_eq!(diagnostics[3].fixing_indices, vec![1, 2]);
+        assert_eq!(diagnostics[4].fixing_indices, vec![2, 3]);
+        assert_eq!(diagnostics[5].fixing_indices, vec![0, 3, 4]);
+    }
+
+    proptest! {
+        #[test]
+        fn parts_match_brute_force(
+            reports in prop::collection::vec(prop::collection::vec(1i32..12, 1..8), 0..30),
+        ) {
+            let input: String = reports
+                .iter()
+                .map(|report| {
+                    let levels: Vec<String> = report.iter().map(i32::to_string).collect();
+                    levels.join(" ") + "\n"
+                })
+                .collect();
+            let count = |rules| reports.iter().filter(|r| brute_force(r, &rules)).count();
+
+            prop_assert_eq!(part1(&input), count(SafetyRules::PART1));
+            prop_assert_eq!(part2(&input), count(SafetyRules::PART2));
+        }
+
+        #[test]
+        fn split_ints_agrees_with_derived(line in "[-+0-9 x]{0,14}") {
+            if let Some(levels) = split_ints(&line, b' ') {
+                prop_assert_eq!(line.parse::<Report>(), Ok(Report { levels }));
+            }
+        }
+
+        #[test]
+        fn generated_inputs_match_brute_force(count in 0usize..40, seed in any::<u64>()) {
+            let input = generate(count, seed);
+            let reports = parse(&input).unwrap();
+            prop_assert_eq!(reports.len(), count);
+            let safe = |rules| reports.iter().filter(|r| brute_force(r, &rules)).count();
+            prop_assert_eq!(part1(&input), safe(SafetyRules::PART1));
+            prop_assert_eq!(part2(&input), safe(SafetyRules::PART2));
+        }
+    }
 }
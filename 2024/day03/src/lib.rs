@@ -1,147 +1,578 @@
-use std::str::Chars;
+use aoc_common::ParseError;
+use aoc_common::rng::Rng;
+use memchr::{memchr, memchr2, memchr3};
+use std::fmt::Write as _;
+use std::ops::Range;
 
-/// Represents a multiplication instruction
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Represents a multiplication instruction found in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Instruction {
-    first: u64,
-    second: u64,
-    enabled: bool,
+    pub first: u64,
+    pub second: u64,
+    pub enabled: bool,
+    /// Byte range of the whole `mul(a,b)` in the input
+    pub span: Range<usize>,
 }
 
 impl Instruction {
-    /// Create a new instruction
-    fn new(first: u64, second: u64, enabled: bool) -> Self {
+    /// Returns the result of the multiplication
+    pub fn result(&self) -> u64 {
+        self.first * self.second
+    }
+}
+
+/// What an opcode does when executed
+#[derive(Debug, Clone, Copy)]
+pub enum Effect {
+    /// Produce a value from the arguments; it only counts while enabled
+    Value(fn(&[u64]) -> u64),
+    /// Set the enabled flag
+    Enable(bool),
+}
+
+/// An instruction of the form `name(a,b,...)` with a fixed number of numeric arguments
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode {
+    pub name: &'static str,
+    pub arity: usize,
+    pub max_digits: usize,
+    pub effect: Effect,
+}
+
+impl Opcode {
+    /// Default maximum number of digits per argument
+    pub const DEFAULT_MAX_DIGITS: usize = 3;
+
+    /// An opcode that computes a value from `arity` arguments
+    pub const fn value(name: &'static str, arity: usize, compute: fn(&[u64]) -> u64) -> Self {
         Self {
-            first,
-            second,
-            enabled,
+            name,
+            arity,
+            max_digits: Self::DEFAULT_MAX_DIGITS,
+            effect: Effect::Value(compute),
         }
     }
 
-    /// Returns the result of the multiplication
-    fn result(&self) -> u64 {
-        self.first * self.second
+    /// An opcode without arguments that enables or disables later instructions
+    pub const fn toggle(name: &'static str, enabled: bool) -> Self {
+        Self {
+            name,
+            arity: 0,
+            max_digits: Self::DEFAULT_MAX_DIGITS,
+            effect: Effect::Enable(enabled),
+        }
     }
+
+    /// Allow between 1 and `max_digits` digits per argument
+    pub const fn with_max_digits(mut self, max_digits: usize) -> Self {
+        self.max_digits = max_digits;
+        self
+    }
+}
+
+/// `mul(a,b)`, `do()` and `don't()`. Part 1 reads only the first; [`Scanner`] borrows
+/// this table directly so that scanning allocates nothing.
+static PUZZLE_OPCODES: [Opcode; 3] = [
+    Opcode::value("mul", 2, mul),
+    Opcode::toggle("do", true),
+    Opcode::toggle("don't", false),
+];
+
+fn mul(args: &[u64]) -> u64 {
+    args[0] * args[1]
 }
 
-/// Check if a sequence of characters matches the expected pattern
-fn chars_match_seq(chars: &mut Chars, pattern: &str) -> bool {
-    let mut chars_copy = chars.clone();
-    for expected in pattern.chars() {
-        match chars_copy.next() {
-            Some(c) if c == expected => {}
-            _ => return false,
+/// The table of opcodes a VM recognises. When several opcodes match at the same
+/// position, the one registered first wins.
+#[derive(Debug, Clone, Default)]
+pub struct InstructionSet {
+    opcodes: Vec<Opcode>,
+    /// Distinct first bytes of the opcode names, to skip to candidates with `memchr`
+    starts: Vec<u8>,
+}
+
+impl InstructionSet {
+    /// An empty instruction set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an opcode to the table
+    pub fn register(mut self, opcode: Opcode) -> Self {
+        let start = opcode.name.bytes().next().unwrap_or(b'(');
+        if !self.starts.contains(&start) {
+            self.starts.push(start);
         }
+        self.opcodes.push(opcode);
+        self
+    }
+
+    /// `mul(a,b)` only, as in part 1
+    pub fn part1() -> Self {
+        Self::new().register(PUZZLE_OPCODES[0])
+    }
+
+    /// `mul(a,b)` plus the `do()` and `don't()` toggles, as in part 2
+    pub fn part2() -> Self {
+        PUZZLE_OPCODES
+            .iter()
+            .fold(Self::new(), |set, &opcode| set.register(opcode))
+    }
+
+    /// Registered opcodes, in priority order
+    pub fn opcodes(&self) -> &[Opcode] {
+        &self.opcodes
     }
 
-    // Consume the matched characters from the original iterator
-    for _ in 0..pattern.len() {
-        chars.next();
+    /// The most arguments any registered opcode takes
+    fn max_arity(&self) -> usize {
+        self.opcodes
+            .iter()
+            .map(|opcode| opcode.arity)
+            .max()
+            .unwrap_or(0)
     }
+}
 
-    true
+/// A recognised instruction: the index of its opcode and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub opcode: usize,
+    pub args: Vec<u64>,
 }
 
-/// Parse a 1-3 digit number
-fn parse_number(chars: &mut Chars) -> Option<u64> {
-    let mut num_str = String::with_capacity(3);
-    let chars_copy = chars.clone();
+/// Outcome of trying to read an opcode at a position
+enum Scan {
+    /// Matched, consuming `len` bytes
+    Match { len: usize },
+    /// Definitely not this opcode
+    NoMatch,
+    /// Consistent so far, but the input ended before a decision could be made
+    Incomplete,
+}
 
-    // Collect 1-3 digits
-    for c in chars_copy {
-        if c.is_ascii_digit() {
-            num_str.push(c);
-            if num_str.len() >= 3 {
-                break;
-            }
-        } else {
-            break;
+/// Match a literal at `pos`, returning the position after it
+fn scan_literal(input: &[u8], pos: usize, literal: &[u8]) -> Result<usize, Scan> {
+    for (k, &expected) in literal.iter().enumerate() {
+        match input.get(pos + k) {
+            None => return Err(Scan::Incomplete),
+            Some(&b) if b == expected => {}
+            Some(_) => return Err(Scan::NoMatch),
         }
     }
 
-    if num_str.is_empty() {
-        return None;
+    Ok(pos + literal.len())
+}
+
+/// Parse a 1 to `max_digits` digit number at `pos`, returning it and the position after it
+fn scan_number(input: &[u8], pos: usize, max_digits: usize) -> Result<(u64, usize), Scan> {
+    let mut value: u64 = 0;
+    let mut len = 0;
+
+    while len < max_digits {
+        match input.get(pos + len) {
+            Some(b) if b.is_ascii_digit() => {
+                value = value
+                    .checked_mul(10)
+                    .and_then(|v| v.checked_add(u64::from(b - b'0')))
+                    .ok_or(Scan::NoMatch)?;
+                len += 1;
+            }
+            None => return Err(Scan::Incomplete),
+            Some(_) => break,
+        }
     }
 
-    // Consume the digits from the original iterator
-    for _ in 0..num_str.len() {
-        chars.next();
+    if len == 0 {
+        return Err(Scan::NoMatch);
     }
 
-    num_str.parse::<u64>().ok()
+    Ok((value, pos + len))
 }
 
-/// Parse mul instruction parameters (x,y)
-fn parse_mul_params(chars: &mut Chars) -> Option<(u64, u64)> {
-    let first = parse_number(chars)?;
+/// Try to read `opcode` from the start of `input`, writing its arguments to the front
+/// of `args`, which must hold at least `opcode.arity` values
+fn scan_opcode(opcode: &Opcode, input: &[u8], args: &mut [u64]) -> Scan {
+    let mut scan = || -> Result<Scan, Scan> {
+        let mut pos = scan_literal(input, 0, opcode.name.as_bytes())?;
+        pos = scan_literal(input, pos, b"(")?;
 
-    // Expect a comma after the first number
-    if chars.next() != Some(',') {
-        return None;
-    }
+        for (k, arg) in args[..opcode.arity].iter_mut().enumerate() {
+            if k > 0 {
+                pos = scan_literal(input, pos, b",")?;
+            }
+            let (value, next) = scan_number(input, pos, opcode.max_digits)?;
+            *arg = value;
+            pos = next;
+        }
+
+        pos = scan_literal(input, pos, b")")?;
+        Ok(Scan::Match { len: pos })
+    };
+
+    scan().unwrap_or_else(|outcome| outcome)
+}
 
-    let second = parse_number(chars)?;
+/// Where the next instruction of a set is in some input
+enum Found {
+    /// The opcode at index `opcode` spanning `len` bytes from `start`
+    Token {
+        start: usize,
+        len: usize,
+        opcode: usize,
+    },
+    /// The input ends inside what may still become an instruction starting here
+    Incomplete(usize),
+    /// No instruction can start in the rest of the input
+    End,
+}
+
+/// Find the first of `opcodes` at or after `pos`, jumping between their first bytes
+/// `starts` with `memchr`, and leave its arguments at the front of `args`. Tokenizer
+/// and Scanner share this grammar.
+fn find_token(
+    opcodes: &[Opcode],
+    starts: &[u8],
+    input: &[u8],
+    mut pos: usize,
+    args: &mut [u64],
+) -> Found {
+    loop {
+        let rest = &input[pos..];
+        let offset = match *starts {
+            [a] => memchr(a, rest),
+            [a, b] => memchr2(a, b, rest),
+            [a, b, c] => memchr3(a, b, c, rest),
+            ref starts => rest.iter().position(|b| starts.contains(b)),
+        };
+        let Some(offset) = offset else {
+            return Found::End;
+        };
 
-    // Expect a closing parenthesis
-    if chars.next() != Some(')') {
-        return None;
+        let start = pos + offset;
+        for (index, opcode) in opcodes.iter().enumerate() {
+            match scan_opcode(opcode, &input[start..], args) {
+                Scan::Match { len } => {
+                    return Found::Token {
+                        start,
+                        len,
+                        opcode: index,
+                    };
+                }
+                Scan::Incomplete => return Found::Incomplete(start),
+                Scan::NoMatch => {}
+            }
+        }
+        pos = start + 1;
     }
+}
 
-    Some((first, second))
+/// Splits a stream of bytes into tokens. Bytes that might still start an
+/// instruction when a chunk ends are kept until the next chunk arrives.
+#[derive(Debug, Clone, Default)]
+pub struct Tokenizer {
+    pending: Vec<u8>,
 }
 
-/// Parse the input to extract valid multiplication instructions
-pub fn parse_instructions(input: &str, handle_conditionals: bool) -> Vec<Instruction> {
-    let mut instructions = Vec::new();
-    let mut chars = input.chars();
-    let mut enabled = true; // Instructions start enabled
-
-    while let Some(ch) = chars.next() {
-        match ch {
-            'm' if chars_match_seq(&mut chars, "ul(") => {
-                if let Some((first, second)) = parse_mul_params(&mut chars) {
-                    instructions.push(Instruction::new(first, second, enabled));
+impl Tokenizer {
+    /// A tokenizer with no buffered input
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tokenize the next chunk of input
+    pub fn feed(&mut self, set: &InstructionSet, chunk: &[u8]) -> Vec<Token> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut tokens = Vec::new();
+        let mut args = vec![0; set.max_arity()];
+        let mut pos = 0;
+
+        let kept = loop {
+            match find_token(&set.opcodes, &set.starts, &self.pending, pos, &mut args) {
+                Found::Token { start, len, opcode } => {
+                    let arity = set.opcodes[opcode].arity;
+                    tokens.push(Token {
+                        opcode,
+                        args: args[..arity].to_vec(),
+                    });
+                    pos = start + len;
                 }
+                Found::Incomplete(start) => break start,
+                Found::End => break self.pending.len(),
             }
-            'd' if handle_conditionals
-                && chars_match_seq(&mut chars, "o(")
-                && chars.next() == Some(')') =>
-            {
-                enabled = true;
+        };
+
+        self.pending.drain(..kept);
+        tokens
+    }
+
+    /// Discard any partial instruction left at the end of the input
+    pub fn finish(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// An executed value-producing instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executed {
+    pub name: &'static str,
+    pub args: Vec<u64>,
+    pub value: u64,
+    pub enabled: bool,
+}
+
+/// Runs instructions from a stream of chunks, keeping the enabled flag and any
+/// partial instruction between chunks
+#[derive(Debug, Clone)]
+pub struct Vm {
+    set: InstructionSet,
+    tokenizer: Tokenizer,
+    enabled: bool,
+    total: u64,
+}
+
+impl Vm {
+    /// A VM that starts enabled with a total of zero
+    pub fn new(set: InstructionSet) -> Self {
+        Self {
+            set,
+            tokenizer: Tokenizer::new(),
+            enabled: true,
+            total: 0,
+        }
+    }
+
+    /// Execute all complete instructions in the next chunk of input
+    pub fn feed(&mut self, chunk: impl AsRef<[u8]>) -> Vec<Executed> {
+        let tokens = self.tokenizer.feed(&self.set, chunk.as_ref());
+        let mut executed = Vec::new();
+
+        for token in tokens {
+            let opcode = &self.set.opcodes()[token.opcode];
+            match opcode.effect {
+                Effect::Enable(enabled) => self.enabled = enabled,
+                Effect::Value(compute) => {
+                    let value = compute(&token.args);
+                    if self.enabled {
+                        self.total += value;
+                    }
+                    executed.push(Executed {
+                        name: opcode.name,
+                        args: token.args,
+                        value,
+                        enabled: self.enabled,
+                    });
+                }
             }
-            'd' if handle_conditionals
-                && chars_match_seq(&mut chars, "on't(")
-                && chars.next() == Some(')') =>
-            {
-                enabled = false;
+        }
+
+        executed
+    }
+
+    /// Signal the end of input and return the total of all enabled values
+    pub fn finish(mut self) -> u64 {
+        self.tokenizer.finish();
+        self.total
+    }
+
+    /// Whether value instructions currently count towards the total
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Total of all enabled values executed so far
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+/// Zero-copy scanner over the input bytes, yielding every `mul(x,y)` with its span.
+/// It reads the same instructions as [`Vm`] running [`InstructionSet::part1`], or
+/// [`InstructionSet::part2`] when tracking `do()`/`don't()`.
+#[derive(Debug, Clone)]
+pub struct Scanner<'a> {
+    input: &'a [u8],
+    pos: usize,
+    enabled: bool,
+    /// A prefix of [`PUZZLE_OPCODES`] and their first bytes
+    opcodes: &'static [Opcode],
+    starts: &'static [u8],
+    /// Arguments of the last match; `mul` takes the most, two
+    args: [u64; 2],
+}
+
+impl<'a> Scanner<'a> {
+    /// Scan `input`, tracking `do()`/`don't()` only if `handle_conditionals` is set.
+    /// Neither this nor iterating allocates.
+    pub fn new(input: &'a [u8], handle_conditionals: bool) -> Self {
+        let (opcodes, starts): (&'static [Opcode], &'static [u8]) = if handle_conditionals {
+            (&PUZZLE_OPCODES, b"md")
+        } else {
+            (&PUZZLE_OPCODES[..1], b"m")
+        };
+        Self {
+            input,
+            pos: 0,
+            enabled: true,
+            opcodes,
+            starts,
+            args: [0; 2],
+        }
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Instruction;
+
+    fn next(&mut self) -> Option<Instruction> {
+        loop {
+            match find_token(
+                self.opcodes,
+                self.starts,
+                self.input,
+                self.pos,
+                &mut self.args,
+            ) {
+                Found::Token { start, len, opcode } => {
+                    self.pos = start + len;
+                    match self.opcodes[opcode].effect {
+                        Effect::Enable(enabled) => self.enabled = enabled,
+                        Effect::Value(_) => {
+                            return Some(Instruction {
+                                first: self.args[0],
+                                second: self.args[1],
+                                enabled: self.enabled,
+                                span: start..self.pos,
+                            });
+                        }
+                    }
+                }
+                // The whole input is here, so a partial instruction never completes
+                Found::Incomplete(_) | Found::End => {
+                    self.pos = self.input.len();
+                    return None;
+                }
             }
-            _ => {} // Skip other characters
         }
     }
+}
 
-    instructions
+/// Parse the input to extract valid multiplication instructions
+pub fn parse_instructions(input: &str, handle_conditionals: bool) -> Vec<Instruction> {
+    Scanner::new(input.as_bytes(), handle_conditionals).collect()
 }
 
 /// Part 1: Find valid multiplications in corrupted memory and sum their results
 pub fn part1(input: &str) -> u64 {
-    parse_instructions(input, false)
-        .iter()
+    Scanner::new(input.as_bytes(), false)
         .map(|instr| instr.result())
         .sum()
 }
 
 /// Part 2: Find valid multiplications in corrupted memory with do/don't control
 pub fn part2(input: &str) -> u64 {
-    parse_instructions(input, true)
-        .iter()
+    Scanner::new(input.as_bytes(), true)
         .filter(|instr| instr.enabled)
         .map(|instr| instr.result())
         .sum()
 }
 
+/// Both answers from a single scan; any input is valid corrupted memory, so this never fails
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    Ok(
+        Scanner::new(input.as_bytes(), true).fold((0, 0), |(all, enabled), instr| {
+            let result = instr.result();
+            (
+                all + result,
+                enabled + if instr.enabled { result } else { 0 },
+            )
+        }),
+    )
+}
+
+/// Text that looks like, but is not, a valid instruction
+const NOISE: &[&str] = &[
+    "mul",
+    "mul(",
+    "mul[3,7]",
+    "mul(4*",
+    "mul ( 2 , 4 )",
+    "mul(1234,5)",
+    "(",
+    ")",
+    ",",
+    "what()",
+    "from()",
+    "select()",
+    "@",
+    "%",
+    "how(",
+    "^",
+    "don't",
+    "do(",
+    "[",
+    "]",
+    "<",
+    ">",
+    "'",
+    "?",
+    " ",
+    "\n",
+];
+
+/// Generate at least `len` bytes of corrupted memory in which roughly `mul_density` of the
+/// fragments are valid `mul(X,Y)` instructions, with a few `do()` and `don't()` among noise
+pub fn generate_memory(len: usize, mul_density: f64, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut memory = String::with_capacity(len + 16);
+
+    while memory.len() < len {
+        if rng.chance(mul_density) {
+            write!(memory, "mul({},{})", rng.range(1..1000), rng.range(1..1000)).unwrap();
+        } else if rng.chance(0.02) {
+            memory.push_str(if rng.chance(0.5) { "do()" } else { "don't()" });
+        } else {
+            let fragment = *rng.choose(NOISE);
+            memory.push_str(fragment);
+        }
+    }
+
+    memory
+}
+
+/// Generate at least `len` bytes of corrupted memory with the density of a real input
+pub fn generate(len: usize, seed: u64) -> String {
+    generate_memory(len, 0.1, seed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the allocations made on each thread, so a test can check it made none
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
 
     const EXAMPLE_INPUT_1: &str =
         "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
@@ -174,4 +605,145 @@ mod tests {
         let instructions = parse_instructions("mul(2,4", false);
         assert_eq!(instructions.len(), 0);
     }
+
+    #[test]
+    fn test_custom_opcodes() {
+        let set = InstructionSet::part2()
+            .register(Opcode::value("add", 2, |args| args[0] + args[1]).with_max_digits(5))
+            .register(Opcode::value("neg", 1, |args| 100 - args[0].min(100)));
+        let mut vm = Vm::new(set);
+        let executed = vm.feed("add(10000,2)mul(1000,2)don't()neg(1)do()neg(90)");
+        let values: Vec<(&str, u64, bool)> = executed
+            .iter()
+            .map(|e| (e.name, e.value, e.enabled))
+            .collect();
+        assert_eq!(
+            values,
+            vec![("add", 10002, true), ("neg", 99, false), ("neg", 10, true)]
+        );
+        assert_eq!(vm.finish(), 10012);
+    }
+
+    #[test]
+    fn test_chunked_input() {
+        // Every possible split point must give the same answer as the whole input
+        for split in 0..=EXAMPLE_INPUT_2.len() {
+            let mut vm = Vm::new(InstructionSet::part2());
+            vm.feed(&EXAMPLE_INPUT_2[..split]);
+            vm.feed(&EXAMPLE_INPUT_2[split..]);
+            assert_eq!(vm.finish(), 48, "split at {}", split);
+        }
+
+        // State carries over one byte at a time
+        let mut vm = Vm::new(InstructionSet::part2());
+        for byte in "don't()mul(2,3)do()mul(4,5)".bytes() {
+            vm.feed([byte]);
+        }
+        assert!(vm.enabled());
+        assert_eq!(vm.total(), 20);
+    }
+
+    #[test]
+    fn test_instruction_spans() {
+        let instructions = parse_instructions(EXAMPLE_INPUT_2, true);
+        let spans: Vec<(&str, bool)> = instructions
+            .iter()
+            .map(|instr| (&EXAMPLE_INPUT_2[instr.span.clone()], instr.enabled))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("mul(2,4)", true),
+                ("mul(5,5)", false),
+                ("mul(11,8)", false),
+                ("mul(8,5)", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanner_does_not_allocate() {
+        let input = generate(10_000, 3);
+        for handle_conditionals in [false, true] {
+            let before = ALLOCATIONS.with(Cell::get);
+            let total: u64 = Scanner::new(input.as_bytes(), handle_conditionals)
+                .filter(|instruction| instruction.enabled)
+                .map(|instruction| instruction.result())
+                .sum();
+            assert_eq!(ALLOCATIONS.with(Cell::get), before);
+            assert!(total > 0);
+        }
+    }
+
+    #[test]
+    fn test_scanner_matches_vm() {
+        let input = "mul(1,2)mmul(3,4)mul(12345,6)domul(7,8)don't()mul(9,9)do()mul(999,1)";
+        let scanned: Vec<(u64, bool)> = Scanner::new(input.as_bytes(), true)
+            .map(|instr| (instr.result(), instr.enabled))
+            .collect();
+        let executed: Vec<(u64, bool)> = Vm::new(InstructionSet::part2())
+            .feed(input)
+            .into_iter()
+            .map(|e| (e.value, e.enabled))
+            .collect();
+        assert_eq!(scanned, executed);
+    }
+
+    /// Naive reference: try to match an instruction at every byte offset
+    fn naive(input: &str, handle_conditionals: bool) -> u64 {
+        let is_number =
+            |s: &str| (1..=3).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+        let mut enabled = true;
+        let mut total = 0;
+
+        for i in 0..input.len() {
+            let rest = &input[i..];
+            if rest.starts_with("do()") {
+                enabled = true;
+            } else if rest.starts_with("don't()") {
+                enabled = false;
+            } else if let Some(args) = rest.strip_prefix("mul(")
+                && let Some((args, _)) = args.split_once(')')
+                && let Some((a, b)) = args.split_once(',')
+                && is_number(a)
+                && is_number(b)
+                && (enabled || !handle_conditionals)
+            {
+                total += a.parse::<u64>().unwrap() * b.parse::<u64>().unwrap();
+            }
+        }
+        total
+    }
+
+    /// Memory made of fragments that often combine into valid or nearly valid instructions
+    fn memory_strategy() -> impl Strategy<Value = String> {
+        let fragments = vec![
+            "mul(", "mul", "(", ")", ",", "1", "42", "999", "1234", "do()", "don't()", "don't",
+            "do", "x", " ", "-", "mul(2,3)",
+        ];
+        prop::collection::vec(prop::sample::select(fragments), 0..40)
+            .prop_map(|parts| parts.concat())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn parts_match_naive(input in memory_strategy()) {
+            prop_assert_eq!(part1(&input), naive(&input, false));
+            prop_assert_eq!(part2(&input), naive(&input, true));
+        }
+
+        #[test]
+        fn generated_memory_matches_naive(
+            len in 0usize..400,
+            density in 0.0f64..0.5,
+            seed in any::<u64>(),
+        ) {
+            let input = generate_memory(len, density, seed);
+            prop_assert!(input.len() >= len);
+            prop_assert_eq!(part1(&input), naive(&input, false));
+            prop_assert_eq!(part2(&input), naive(&input, true));
+        }
+    }
 }
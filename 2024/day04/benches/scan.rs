@@ -0,0 +1,38 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use day04::{
+    Direction, Stencil, Symmetry, X_MAS, count_x_mas, count_xmas, find_stencil, find_word,
+    parse_grid,
+};
+
+fn bench_part1(c: &mut Criterion) {
+    // The size of a real puzzle input
+    let input = day04::generate(140, 2024);
+    let mut group = c.benchmark_group("part1");
+
+    group.bench_function("find_word", |b| {
+        b.iter(|| {
+            let grid = parse_grid(black_box(&input)).unwrap();
+            find_word(&grid, "XMAS", &Direction::ALL).len()
+        })
+    });
+    group.bench_function("count_xmas", |b| b.iter(|| count_xmas(black_box(&input))));
+    group.finish();
+}
+
+fn bench_part2(c: &mut Criterion) {
+    let input = day04::generate(140, 2024);
+    let stencil = Stencil::parse(X_MAS);
+    let mut group = c.benchmark_group("part2");
+
+    group.bench_function("find_stencil", |b| {
+        b.iter(|| {
+            let grid = parse_grid(black_box(&input)).unwrap();
+            find_stencil(&grid, &stencil, Symmetry::Rotations).len()
+        })
+    });
+    group.bench_function("count_x_mas", |b| b.iter(|| count_x_mas(black_box(&input))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_part1, bench_part2);
+criterion_main!(benches);
@@ -1,13 +1,12 @@
+use aoc_common::ParseError;
+use aoc_common::rng::Rng;
+
 /// Represents a character in the grid or None for out of bounds
 type GridChar = Option<char>;
 
-/// Parse the input string into a 2D character grid
-fn parse_grid(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.chars().collect())
-        .collect()
+/// Parse the input string into a 2D character grid, rejecting ragged rows
+pub fn parse_grid(input: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    aoc_common::grid::parse_rows(input)
 }
 
 /// Get character at position (i,j) in the grid, returning None if out of bounds
@@ -19,93 +18,356 @@ fn get_char(grid: &[Vec<char>], i: isize, j: isize) -> GridChar {
     }
 }
 
-/// Part 1: Count the number of "XMAS" patterns in the grid in all 8 directions
-pub fn part1(input: &str) -> u64 {
-    let grid: Vec<Vec<char>> = parse_grid(input);
+/// One of the 8 directions a word can be read in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// All 8 directions, clockwise from North
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The 4 diagonal directions
+    pub const DIAGONALS: [Direction; 4] = [
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    /// Row and column offset of a single step
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+/// A word found in the grid, starting at (row, col) and read in `direction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WordMatch {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+}
+
+/// Find every occurrence of `word` reading in any of the given directions
+pub fn find_word(grid: &[Vec<char>], word: &str, directions: &[Direction]) -> Vec<WordMatch> {
+    let word: Vec<char> = word.chars().collect();
+    let mut matches = Vec::new();
 
-    let mut count: u64 = 0;
+    if word.is_empty() {
+        return matches;
+    }
 
     for i in 0..grid.len() {
         for j in 0..grid[i].len() {
-            let i_signed: isize = i as isize;
-            let j_signed: isize = j as isize;
-
-            // Check all 8 directions
-            let directions: Vec<Vec<GridChar>> = vec![
-                // North
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed - d, j_signed))
-                    .collect(),
-                // Northeast
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed - d, j_signed + d))
-                    .collect(),
-                // East
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed, j_signed + d))
-                    .collect(),
-                // Southeast
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed + d, j_signed + d))
-                    .collect(),
-                // South
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed + d, j_signed))
-                    .collect(),
-                // Southwest
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed + d, j_signed - d))
-                    .collect(),
-                // West
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed, j_signed - d))
-                    .collect(),
-                // Northwest
-                (0..4)
-                    .map(|d| get_char(&grid, i_signed - d, j_signed - d))
-                    .collect(),
-            ];
-
-            // Check for "XMAS" pattern in each direction
-            for direction in directions {
-                if direction == vec![Some('X'), Some('M'), Some('A'), Some('S')] {
-                    count += 1;
+            if grid[i][j] != word[0] {
+                continue;
+            }
+
+            for &direction in directions {
+                let (di, dj) = direction.delta();
+                let found = word.iter().enumerate().all(|(d, &expected)| {
+                    let d = d as isize;
+                    get_char(grid, i as isize + di * d, j as isize + dj * d) == Some(expected)
+                });
+
+                if found {
+                    matches.push(WordMatch {
+                        row: i,
+                        col: j,
+                        direction,
+                    });
                 }
             }
         }
     }
 
-    count
+    matches
 }
 
-/// Check if three characters form "MAS"
-fn is_mas(c1: GridChar, c2: GridChar, c3: GridChar) -> bool {
-    c1 == Some('M') && c2 == Some('A') && c3 == Some('S')
+/// Which transformations of a stencil should also be searched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Only the stencil as given
+    Identity,
+    /// The stencil and its 90, 180 and 270 degree rotations
+    Rotations,
+    /// All rotations and their mirror images
+    RotationsAndReflections,
 }
 
-/// Part 2: Count characters forming an X pattern with "MAS" on opposite sides
-pub fn part2(input: &str) -> u64 {
-    let grid: Vec<Vec<char>> = parse_grid(input);
+/// A rectangular 2D pattern where `None` cells match any character
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stencil {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+impl Stencil {
+    /// Character used for wildcard cells in `Stencil::parse`
+    pub const WILDCARD: char = '.';
+
+    /// Parse a stencil from lines of text, using `.` as a wildcard.
+    /// Short rows are padded with wildcards.
+    pub fn parse(pattern: &str) -> Self {
+        let rows: Vec<&str> = pattern
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let cells = rows
+            .iter()
+            .map(|row| {
+                let mut cells: Vec<Option<char>> = row
+                    .chars()
+                    .map(|c| (c != Self::WILDCARD).then_some(c))
+                    .collect();
+                cells.resize(width, None);
+                cells
+            })
+            .collect();
+
+        Self { cells }
+    }
+
+    /// Number of rows in the stencil
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of columns in the stencil
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    /// Rotate the stencil 90 degrees clockwise
+    pub fn rotate(&self) -> Self {
+        let (height, width) = (self.height(), self.width());
+        let cells = (0..width)
+            .map(|i| (0..height).map(|j| self.cells[height - 1 - j][i]).collect())
+            .collect();
+
+        Self { cells }
+    }
+
+    /// Mirror the stencil left to right
+    pub fn reflect(&self) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+
+        Self { cells }
+    }
+
+    /// Distinct transformations of the stencil allowed by `symmetry`
+    pub fn variants(&self, symmetry: Symmetry) -> Vec<Stencil> {
+        let mut candidates = vec![self.clone()];
+        if symmetry != Symmetry::Identity {
+            for _ in 0..3 {
+                let next = candidates[candidates.len() - 1].rotate();
+                candidates.push(next);
+            }
+        }
+        if symmetry == Symmetry::RotationsAndReflections {
+            let reflected: Vec<Stencil> = candidates.iter().map(Stencil::reflect).collect();
+            candidates.extend(reflected);
+        }
+
+        let mut variants: Vec<Stencil> = Vec::new();
+        for candidate in candidates {
+            if !variants.contains(&candidate) {
+                variants.push(candidate);
+            }
+        }
+
+        variants
+    }
+
+    /// Offsets of the non-wildcard cells, relative to the top-left corner
+    pub fn fixed_cells(&self) -> Vec<(usize, usize)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(di, cells)| {
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cell)| cell.is_some())
+                    .map(move |(dj, _)| (di, dj))
+            })
+            .collect()
+    }
 
-    let mut count: u64 = 0;
+    /// Check whether the stencil matches with its top-left corner at (row, col)
+    fn matches_at(&self, grid: &[Vec<char>], row: usize, col: usize) -> bool {
+        self.cells.iter().enumerate().all(|(di, cells)| {
+            cells.iter().enumerate().all(|(dj, &cell)| {
+                let actual = get_char(grid, (row + di) as isize, (col + dj) as isize);
+                match cell {
+                    Some(expected) => actual == Some(expected),
+                    None => actual.is_some(),
+                }
+            })
+        })
+    }
+}
+
+/// A stencil found in the grid with its top-left corner at (row, col).
+/// `variant` indexes into `Stencil::variants` for the symmetry that was searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StencilMatch {
+    pub row: usize,
+    pub col: usize,
+    pub variant: usize,
+}
+
+/// Find every placement of the stencil, or any of its allowed transformations, in the grid
+pub fn find_stencil(
+    grid: &[Vec<char>],
+    stencil: &Stencil,
+    symmetry: Symmetry,
+) -> Vec<StencilMatch> {
+    let variants = stencil.variants(symmetry);
+    let mut matches = Vec::new();
 
     for i in 0..grid.len() {
         for j in 0..grid[i].len() {
-            let i_signed: isize = i as isize;
-            let j_signed: isize = j as isize;
-            let current: GridChar = Some(grid[i][j]);
+            for (variant, pattern) in variants.iter().enumerate() {
+                if pattern.matches_at(grid, i, j) {
+                    matches.push(StencilMatch {
+                        row: i,
+                        col: j,
+                        variant,
+                    });
+                }
+            }
+        }
+    }
+
+    matches
+}
+
+/// Stencil for two "MAS" crossing in an X; part 2 searches all its rotations
+pub const X_MAS: &str = "M.S\n.A.\nM.S";
+
+/// Split the input into byte rows without copying
+fn byte_rows(input: &str) -> Vec<&[u8]> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::as_bytes)
+        .collect()
+}
+
+/// Split the input into byte rows without copying, checking that the grid is
+/// rectangular as [`parse_grid`] does
+pub fn parse_bytes(input: &str) -> Result<Vec<&[u8]>, ParseError> {
+    let mut width = None;
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let columns = line.chars().count();
+        let expected = *width.get_or_insert(columns);
+        if columns != expected {
+            return Err(ParseError::at(
+                input,
+                line,
+                format!("row has {} columns, expected {}", columns, expected),
+            ));
+        }
+    }
+    Ok(byte_rows(input))
+}
+
+/// Get the byte at position (i,j), returning 0 if out of bounds
+#[inline]
+fn byte_at(rows: &[&[u8]], i: isize, j: isize) -> u8 {
+    if i < 0 || j < 0 {
+        return 0;
+    }
+    rows.get(i as usize)
+        .and_then(|row| row.get(j as usize))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Count "XMAS" in all 8 directions by anchoring on each `X` and comparing in place
+pub fn count_xmas(input: &str) -> u64 {
+    xmas_in(&byte_rows(input))
+}
+
+/// [`count_xmas`] over rows that are already split
+fn xmas_in(rows: &[&[u8]]) -> u64 {
+    let mut count = 0;
+
+    for (i, row) in rows.iter().enumerate() {
+        for (j, _) in row.iter().enumerate().filter(|&(_, &b)| b == b'X') {
+            let (i, j) = (i as isize, j as isize);
+            for direction in Direction::ALL {
+                let (di, dj) = direction.delta();
+                if byte_at(rows, i + di, j + dj) == b'M'
+                    && byte_at(rows, i + 2 * di, j + 2 * dj) == b'A'
+                    && byte_at(rows, i + 3 * di, j + 3 * dj) == b'S'
+                {
+                    count += 1;
+                }
+            }
+        }
+    }
 
-            // Get characters in diagonal positions
-            let ne: GridChar = get_char(&grid, i_signed - 1, j_signed + 1);
-            let nw: GridChar = get_char(&grid, i_signed - 1, j_signed - 1);
-            let se: GridChar = get_char(&grid, i_signed + 1, j_signed + 1);
-            let sw: GridChar = get_char(&grid, i_signed + 1, j_signed - 1);
+    count
+}
 
-            // Check if they form X patterns with "MAS" on opposite sides
-            let condition1: bool = is_mas(ne, current, sw) || is_mas(sw, current, ne);
-            let condition2: bool = is_mas(nw, current, se) || is_mas(se, current, nw);
+/// Count X-MAS crosses by anchoring on each `A` and checking both diagonals in place
+pub fn count_x_mas(input: &str) -> u64 {
+    x_mas_in(&byte_rows(input))
+}
 
-            if condition1 && condition2 {
+/// [`count_x_mas`] over rows that are already split
+fn x_mas_in(rows: &[&[u8]]) -> u64 {
+    let is_mas = |a: u8, b: u8| (a == b'M' && b == b'S') || (a == b'S' && b == b'M');
+    let mut count = 0;
+
+    for (i, row) in rows.iter().enumerate() {
+        for (j, _) in row.iter().enumerate().filter(|&(_, &b)| b == b'A') {
+            let (i, j) = (i as isize, j as isize);
+            let nw = byte_at(rows, i - 1, j - 1);
+            let ne = byte_at(rows, i - 1, j + 1);
+            let sw = byte_at(rows, i + 1, j - 1);
+            let se = byte_at(rows, i + 1, j + 1);
+
+            if is_mas(nw, se) && is_mas(ne, sw) {
                 count += 1;
             }
         }
@@ -114,9 +376,42 @@ pub fn part2(input: &str) -> u64 {
     count
 }
 
+/// Part 1: Count the number of "XMAS" patterns in the grid in all 8 directions
+pub fn part1(input: &str) -> u64 {
+    count_xmas(input)
+}
+
+/// Part 2: Count characters forming an X pattern with "MAS" on opposite sides
+pub fn part2(input: &str) -> u64 {
+    count_x_mas(input)
+}
+
+/// Both answers from a single split, or the error that `part1` and `part2` would panic with
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    let rows = parse_bytes(input)?;
+    Ok((xmas_in(&rows), x_mas_in(&rows)))
+}
+
+/// Generate a `size` by `size` word search over the letters of "XMAS"
+pub fn generate(size: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut input = String::with_capacity((size + 1) * size);
+
+    for _ in 0..size {
+        for _ in 0..size {
+            input.push(*rng.choose(&['X', 'M', 'A', 'S']));
+        }
+        input.push('\n');
+    }
+
+    input
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_common::metamorphic;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "MMMSXXMASM\n\
          MSAMXMSMSA\n\
@@ -138,4 +433,111 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 9);
     }
+
+    #[test]
+    fn test_answers_reject_ragged_grids() {
+        assert_eq!(answers(EXAMPLE_INPUT), Ok((18, 9)));
+        let ragged = "XMAS\nXM\nXMAS";
+        assert_eq!(answers(ragged), Err(parse_grid(ragged).unwrap_err()));
+    }
+
+    #[test]
+    fn test_find_word_directions() {
+        let grid = parse_grid("XMAS\nMM..\nA.A.\nS..S").unwrap();
+        let matches = find_word(&grid, "XMAS", &Direction::ALL);
+        assert_eq!(matches.len(), 3);
+        assert!(matches.iter().all(|m| (m.row, m.col) == (0, 0)));
+        assert_eq!(parse_grid("XMAS\nMM").unwrap_err().line, 2);
+
+        let matches = find_word(&grid, "XMAS", &Direction::DIAGONALS);
+        assert_eq!(
+            matches,
+            vec![WordMatch {
+                row: 0,
+                col: 0,
+                direction: Direction::SouthEast,
+            }]
+        );
+
+        let matches = find_word(&grid, "SAMX", &[Direction::East]);
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_stencil_variants() {
+        let stencil = Stencil::parse(X_MAS);
+        assert_eq!(stencil.variants(Symmetry::Identity).len(), 1);
+        assert_eq!(stencil.variants(Symmetry::Rotations).len(), 4);
+        assert_eq!(stencil.variants(Symmetry::RotationsAndReflections).len(), 4);
+
+        let l_shape = Stencil::parse("AB\nC.");
+        assert_eq!(l_shape.rotate(), Stencil::parse("CA\n.B"));
+        assert_eq!(l_shape.reflect(), Stencil::parse("BA\n.C"));
+        assert_eq!(l_shape.variants(Symmetry::RotationsAndReflections).len(), 8);
+        assert_eq!(l_shape.fixed_cells(), vec![(0, 0), (0, 1), (1, 0)]);
+
+        let grid = parse_grid(EXAMPLE_INPUT).unwrap();
+        let matches = find_stencil(&grid, &stencil, Symmetry::Rotations);
+        assert!(matches.contains(&StencilMatch {
+            row: 0,
+            col: 1,
+            variant: 0,
+        }));
+    }
+
+    /// Rectangular grids over a small alphabet so that matches are common
+    fn grid_strategy() -> impl Strategy<Value = String> {
+        (1usize..16, 1usize..16).prop_flat_map(|(height, width)| {
+            prop::collection::vec(
+                prop::collection::vec(prop::sample::select(vec!['X', 'M', 'A', 'S', '.']), width),
+                height,
+            )
+            .prop_map(|rows| {
+                rows.into_iter()
+                    .map(|row| row.into_iter().collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        })
+    }
+
+    #[test]
+    fn test_metamorphic_example() {
+        let transforms = metamorphic::grid_symmetries();
+        metamorphic::check_both(part1, part2, EXAMPLE_INPUT, &transforms).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn count_xmas_matches_reference(input in grid_strategy()) {
+            let grid = parse_grid(&input).unwrap();
+            let expected = find_word(&grid, "XMAS", &Direction::ALL).len() as u64;
+            prop_assert_eq!(count_xmas(&input), expected);
+        }
+
+        #[test]
+        fn count_x_mas_matches_reference(input in grid_strategy()) {
+            let grid = parse_grid(&input).unwrap();
+            let expected =
+                find_stencil(&grid, &Stencil::parse(X_MAS), Symmetry::Rotations).len() as u64;
+            prop_assert_eq!(count_x_mas(&input), expected);
+        }
+
+        #[test]
+        fn generated_grids_match_reference(size in 1usize..20, seed in any::<u64>()) {
+            let input = generate(size, seed);
+            let grid = parse_grid(&input).unwrap();
+            prop_assert_eq!((grid.len(), grid[0].len()), (size, size));
+            let expected = find_word(&grid, "XMAS", &Direction::ALL).len() as u64;
+            prop_assert_eq!(count_xmas(&input), expected);
+        }
+
+        #[test]
+        fn generated_inputs_are_invariant(size in 1usize..16, seed in any::<u64>()) {
+            let transforms = metamorphic::grid_symmetries();
+            let input = generate(size, seed);
+            let checked = metamorphic::check_both(part1, part2, &input, &transforms);
+            prop_assert_eq!(checked, Ok(()));
+        }
+    }
 }
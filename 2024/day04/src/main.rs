@@ -1,8 +1,9 @@
-use day04::{part1, part2};
+use day04::answers;
 use std::io::{self, Read};
+use std::process::ExitCode;
 use std::time::Instant;
 
-fn main() {
+fn main() -> ExitCode {
     // Read input from stdin
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
@@ -10,9 +11,15 @@ fn main() {
 
     let start_time = Instant::now();
 
-    println!("Part 1: {}", part1(input));
-    println!("Part 2: {}", part2(input));
+    let (part1, part2) = match answers(input) {
+        Ok(answers) => answers,
+        Err(err) => return aoc_common::parse::report(&err, input, "<stdin>"),
+    };
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
 
     let elapsed = start_time.elapsed();
     println!("Elapsed time: {:.4} seconds", elapsed.as_secs_f64());
+
+    ExitCode::SUCCESS
 }
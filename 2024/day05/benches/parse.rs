@@ -0,0 +1,40 @@
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::path::Path;
+
+/// The real puzzle input when it has been downloaded, else a generated one of the same shape
+fn load_input() -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../inputs/2024/day05.txt");
+    std::fs::read_to_string(path).unwrap_or_else(|_| day05::generate(200, 2024))
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let input = load_input();
+    let mut group = c.benchmark_group("day05_parse");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    // The previous approach: str::parse on each field split out of the line
+    group.bench_function("str_parse", |b| {
+        b.iter(|| {
+            let (rules, updates) = black_box(&input).split_once("\n\n").unwrap();
+            let rules: Vec<(i32, i32)> = rules
+                .lines()
+                .map(|line| {
+                    let (before, after) = line.split_once('|').unwrap();
+                    (before.parse().unwrap(), after.parse().unwrap())
+                })
+                .collect();
+            let updates: Vec<Vec<i32>> = updates
+                .lines()
+                .map(|line| line.split(',').map(|page| page.parse().unwrap()).collect())
+                .collect();
+            (rules, updates)
+        })
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| day05::parse(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -1,151 +1,722 @@
-use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use aoc_common::format::sections;
+use aoc_common::ints::{parse_int, split_ints};
+use aoc_common::rng::Rng;
+use aoc_common::{AocParse, ParseError};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write as _;
+use std::str::FromStr;
 
 type Rule = (i32, i32);
 type UpdateList = Vec<i32>;
-type FollowingMap = HashMap<i32, HashSet<i32>>;
-type UpdatePartition<'a> = (Vec<&'a UpdateList>, Vec<&'a UpdateList>);
 
-/// Parse the input string into rules and updates
-fn parse_input(input: &str) -> (Vec<Rule>, Vec<UpdateList>) {
-    let lines: Vec<&str> = input.lines().collect();
+/// A rule line `X|Y`: page X must be printed before page Y
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AocParse)]
+#[aoc(format = "{before: label='page number'}|{after: label='page number'}")]
+struct RuleLine {
+    before: i32,
+    after: i32,
+}
+
+/// An update line: comma-separated page numbers
+#[derive(Debug, Clone, PartialEq, Eq, AocParse)]
+#[aoc(format = "{pages: sep=',', label='page number'}")]
+struct UpdateLine {
+    pages: UpdateList,
+}
+
+/// The puzzle input: rules, a blank line, then updates
+#[derive(Debug, AocParse)]
+#[aoc(sections)]
+struct PrintQueue {
+    rules: Vec<OrderingRule>,
+    #[aoc(default)]
+    updates: Vec<PageUpdate>,
+}
+
+/// A line of the rules section, read by [`parse_rule`]
+#[derive(Debug)]
+struct OrderingRule(Rule);
+
+impl FromStr for OrderingRule {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        parse_rule(line).map(OrderingRule)
+    }
+}
+
+/// A line of the updates section, read by [`parse_update`]
+#[derive(Debug)]
+struct PageUpdate(UpdateList);
+
+impl FromStr for PageUpdate {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        parse_update(line).map(PageUpdate)
+    }
+}
+
+/// Parse a rule line, falling back to the derived parser to explain a malformed one
+fn parse_rule(line: &str) -> Result<Rule, ParseError> {
+    if let Some(rule) = quick_rule(line) {
+        return Ok(rule);
+    }
 
-    // Find the boundary between rules and updates
-    let mut boundary_index = 0;
-    for (i, line) in lines.iter().enumerate() {
-        if line.contains(',') {
-            boundary_index = i;
-            break;
+    // An update here means the blank line before the updates is missing
+    if line.contains(',') && !line.contains('|') {
+        return Err(ParseError::at(
+            line,
+            line,
+            "update found among the rules; updates must follow a blank line",
+        ));
+    }
+    line.parse::<RuleLine>()
+        .map(|rule| (rule.before, rule.after))
+}
+
+/// Parse a well-formed rule without building errors, or None to leave it to the derived parser
+fn quick_rule(line: &str) -> Option<Rule> {
+    let (before, after) = line.split_once('|')?;
+    Some((parse_int(before)?, parse_int(after)?))
+}
+
+/// Parse an update line, falling back to the derived parser to explain a malformed one
+fn parse_update(line: &str) -> Result<UpdateList, ParseError> {
+    if let Some(pages) = split_ints(line, b',') {
+        return Ok(pages);
+    }
+
+    if line.contains('|') {
+        return Err(ParseError::at(
+            line,
+            line,
+            "rule found among the updates; rules must come before the blank line",
+        ));
+    }
+    line.parse::<UpdateLine>().map(|update| update.pages)
+}
+
+/// Parse the input string into rules and updates.
+///
+/// The rules come first, then a blank line and the updates. The updates section
+/// may be missing entirely; anything after it is rejected.
+pub fn parse(input: &str) -> Result<(Vec<Rule>, Vec<UpdateList>), ParseError> {
+    let queue: PrintQueue = input.parse()?;
+    Ok((
+        queue.rules.into_iter().map(|rule| rule.0).collect(),
+        queue.updates.into_iter().map(|update| update.0).collect(),
+    ))
+}
+
+/// Error returned when the rules restricted to an update contain a cycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// Pages forming the cycle, in rule order; the last page must precede the first
+    pub pages: Vec<i32>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ordering rules contain a cycle: ")?;
+        for page in &self.pages {
+            write!(f, "{} -> ", page)?;
+        }
+        match self.pages.first() {
+            Some(first) => write!(f, "{}", first),
+            None => Ok(()),
         }
     }
+}
 
-    let rules_lines = &lines[..boundary_index];
-    let updates_lines = &lines[boundary_index..]; // Parse rules
-    let rules: Vec<Rule> = rules_lines
-        .iter()
-        .filter(|line| !line.trim().is_empty())
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() == 2 {
-                let p = parts[0].trim().parse::<i32>().ok()?;
-                let q = parts[1].trim().parse::<i32>().ok()?;
-                Some((p, q))
-            } else {
-                None
+impl std::error::Error for CycleError {}
+
+/// Precedence graph built from the `X|Y` rules
+#[derive(Debug, Clone, Default)]
+pub struct PageOrdering {
+    successors: HashMap<i32, HashSet<i32>>,
+}
+
+impl PageOrdering {
+    /// Build the precedence graph from a list of rules
+    pub fn new(rules: &[Rule]) -> Self {
+        let mut successors: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for &(p, q) in rules {
+            successors.entry(p).or_default().insert(q);
+        }
+
+        Self { successors }
+    }
+
+    /// Returns true if a rule states that `p` must be printed before `q`
+    pub fn precedes(&self, p: i32, q: i32) -> bool {
+        self.successors.get(&p).is_some_and(|v| v.contains(&q))
+    }
+
+    /// Returns true if no rule is broken by the update; pages without a rule
+    /// between them may appear in any order
+    pub fn is_ordered(&self, update: &[i32]) -> bool {
+        update
+            .iter()
+            .enumerate()
+            .all(|(i, &p)| update[i + 1..].iter().all(|&q| !self.precedes(q, p)))
+    }
+
+    /// Reorder an update by topological sort on the rules between its pages.
+    /// Pages that are not constrained relative to each other keep their original order.
+    pub fn sort(&self, update: &[i32]) -> Result<UpdateList, CycleError> {
+        let order = self.sorted_positions(update)?;
+        Ok(order.into_iter().map(|i| update[i]).collect())
+    }
+
+    /// Induced subgraph over positions in the update: the positions each one must precede
+    fn successors_within(&self, update: &[i32]) -> Vec<Vec<usize>> {
+        update
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                (0..update.len())
+                    .filter(|&j| i != j && self.precedes(p, update[j]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Positions of the fewest pages that must move for the update to follow the rules,
+    /// assuming the rules between its pages have no cycle.
+    ///
+    /// Position `i` is inverted against a later `j` when the rules, followed through other
+    /// pages of the update, put page `j` first. Inversion is a partial order, and the pages
+    /// that can stay are a largest set with no inversion among them: by Dilworth's theorem
+    /// and König's, the positions left unreached on one side of a maximum matching of
+    /// inversions.
+    fn pages_to_move(&self, update: &[i32]) -> Vec<usize> {
+        let n = update.len();
+        let successors = self.successors_within(update);
+
+        // reaches[i][j]: the page at i must come before the page at j
+        let mut reaches = vec![vec![false; n]; n];
+        for (start, reached) in reaches.iter_mut().enumerate() {
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for &j in &successors[i] {
+                    if !reached[j] {
+                        reached[j] = true;
+                        stack.push(j);
+                    }
+                }
             }
-        })
-        .collect();
+        }
+        let inverted = |i: usize, j: usize| i < j && reaches[j][i];
 
-    // Parse updates
-    let updates: Vec<UpdateList> = updates_lines
-        .iter()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| {
-            line.split(',')
-                .filter_map(|x| x.trim().parse::<i32>().ok())
-                .collect()
+        // Kuhn's augmenting paths: matched[j] is the earlier position matched to j
+        fn augment(
+            i: usize,
+            n: usize,
+            inverted: &impl Fn(usize, usize) -> bool,
+            matched: &mut [Option<usize>],
+            seen: &mut [bool],
+        ) -> bool {
+            for j in 0..n {
+                if inverted(i, j) && !seen[j] {
+                    seen[j] = true;
+                    if matched[j].is_none_or(|k| augment(k, n, inverted, matched, seen)) {
+                        matched[j] = Some(i);
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
+        let mut matched = vec![None; n];
+        let mut left: Vec<bool> = (0..n)
+            .map(|i| !augment(i, n, &inverted, &mut matched, &mut vec![false; n]))
+            .collect();
+
+        // Alternating paths from unmatched earlier positions: out along inversions, back
+        // along the matching
+
+        let mut right = vec![false; n];
+        let mut stack: Vec<usize> = (0..n).filter(|&i| left[i]).collect();
+        while let Some(i) = stack.pop() {
+            for j in 0..n {
+                if !inverted(i, j) || right[j] {
+                    continue;
+                }
+                right[j] = true;
+                if let Some(k) = matched[j]
+                    && !left[k]
+                {
+                    left[k] = true;
+                    stack.push(k);
+                }
+            }
+        }
+
+        (0..n).filter(|&i| !left[i] || right[i]).collect()
+    }
+
+    /// Topological order of the update, as positions into the original update
+    fn sorted_positions(&self, update: &[i32]) -> Result<Vec<usize>, CycleError> {
+        let n = update.len();
+        let successors = self.successors_within(update);
+        let mut in_degree = vec![0; n];
+        for &j in successors.iter().flatten() {
+            in_degree[j] += 1;
+        }
+
+        // Kahn's algorithm, always taking the earliest available position
+        let mut ready: BTreeSet<usize> = (0..n).filter(|&i| in_degree[i] == 0).collect();
+        let mut sorted = Vec::with_capacity(n);
+
+        while let Some(i) = ready.pop_first() {
+            sorted.push(i);
+            for &j in &successors[i] {
+                in_degree[j] -= 1;
+                if in_degree[j] == 0 {
+                    ready.insert(j);
+                }
+            }
+        }
+
+        if sorted.len() == n {
+            return Ok(sorted);
+        }
+
+        // Every remaining position has a remaining predecessor, so walking
+        // predecessors must eventually revisit a position on a cycle
+        let predecessor = |j: usize| {
+            (0..n)
+                .find(|&i| in_degree[i] > 0 && successors[i].contains(&j))
+                .expect("remaining page has a remaining predecessor")
+        };
+
+        let mut seen = vec![false; n];
+        let mut current = (0..n).find(|&i| in_degree[i] > 0).unwrap();
+        while !seen[current] {
+            seen[current] = true;
+            current = predecessor(current);
+        }
+
+        let start = current;
+        let mut pages = vec![update[start]];
+        current = predecessor(start);
+        while current != start {
+            pages.push(update[current]);
+            current = predecessor(current);
+        }
+        pages.reverse();
+
+        Err(CycleError { pages })
+    }
+
+    /// List every rule broken by the update, in order of the earlier position
+    pub fn violations(&self, update: &[i32]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (i, &q) in update.iter().enumerate() {
+            for (j, &p) in update.iter().enumerate().skip(i + 1) {
+                if self.precedes(p, q) {
+                    violations.push(Violation {
+                        rule: (p, q),
+                        before_position: j,
+                        after_position: i,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Explain why an update is invalid, or None if it is correctly ordered
+    /// and has a well-defined middle page
+    pub fn diagnose(&self, update: &[i32]) -> Option<Diagnostic> {
+        let violations = self.violations(update);
+        let mut errors = Vec::new();
+        if let Err(err) = middle_page(update) {
+            errors.push(err);
+        }
+
+        if violations.is_empty() && errors.is_empty() {
+            return None;
+        }
+
+        let (reordered, moved) = match self.sorted_positions(update) {
+            Ok(order) => {
+                let moved = self
+                    .pages_to_move(update)
+                    .into_iter()
+                    .map(|i| update[i])
+                    .collect();
+                (Some(order.into_iter().map(|i| update[i]).collect()), moved)
+            }
+            Err(err) => {
+                errors.push(UpdateError::Cycle(err));
+                (None, Vec::new())
+            }
+        };
+
+        Some(Diagnostic {
+            update: update.to_vec(),
+            violations,
+            reordered,
+            moved,
+            errors,
         })
-        .collect();
+    }
+}
 
-    (rules, updates)
+/// A rule `X|Y` broken by an update, because Y is printed before X
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// The broken rule `(X, Y)`
+    pub rule: Rule,
+    /// Position of X, the page that should have come first
+    pub before_position: usize,
+    /// Position of Y, the page that was printed too early
+    pub after_position: usize,
 }
-/// Split updates into correct and incorrect based on rules
-fn split_updates<'a>(
-    rules: &[Rule],
-    updates: &'a [UpdateList],
-) -> (FollowingMap, UpdatePartition<'a>) {
-    // Extract unique pages
-    let pages: Vec<i32> = rules
-        .iter()
-        .flat_map(|(p, q)| vec![*p, *q])
-        .sorted()
-        .dedup()
-        .collect();
 
-    // Debug assertion - can be commented out in production
-    for (p, q) in pages.iter().cartesian_product(pages.iter()) {
-        assert!(
-            p == q
-                || rules.iter().any(|(x, y)| x == p && y == q)
-                || rules.iter().any(|(x, y)| x == q && y == p)
-        );
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}|{} broken: {} at position {} is printed before {} at position {}",
+            self.rule.0,
+            self.rule.1,
+            self.rule.1,
+            self.after_position,
+            self.rule.0,
+            self.before_position
+        )
     }
+}
 
-    // Build the following map
-    let mut following: HashMap<i32, HashSet<i32>> = HashMap::new();
-    for &(p, q) in rules {
-        following.entry(p).or_default().insert(q);
+/// Problems that prevent an update from producing an answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateError {
+    /// The update has an even number of pages, so its middle page is ambiguous
+    EvenLength(usize),
+    /// The update has no pages at all
+    Empty,
+    /// The rules between the update's pages cannot be satisfied
+    Cycle(CycleError),
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::EvenLength(len) => {
+                write!(
+                    f,
+                    "update has {} pages, so the middle page is ambiguous",
+                    len
+                )
+            }
+            UpdateError::Empty => write!(f, "update has no pages"),
+            UpdateError::Cycle(err) => err.fmt(f),
+        }
     }
+}
 
-    // Check if updates are valid
-    let check_updates = |update_list: &UpdateList| -> bool {
-        for (i, &p) in update_list.iter().enumerate() {
-            for &q in update_list.iter().skip(i + 1) {
-                let p_q = following.get(&p).map(|v| v.contains(&q)).unwrap_or(true);
+impl std::error::Error for UpdateError {}
 
-                let q_p = following.get(&q).map(|v| !v.contains(&p)).unwrap_or(true);
+/// Everything wrong with a single update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The update as printed
+    pub update: UpdateList,
+    /// Rules broken by the update
+    pub violations: Vec<Violation>,
+    /// Correct ordering, or None if the rules contain a cycle
+    pub reordered: Option<UpdateList>,
+    /// The fewest pages that must move to fix the update: those outside a longest
+    /// subsequence that already follows the rules, including rules implied through other
+    /// pages of the update. Moving only these may give an order other than `reordered`.
+    /// Empty if the rules contain a cycle.
+    pub moved: Vec<i32>,
+    /// Errors such as an ambiguous middle page or a cycle
+    pub errors: Vec<UpdateError>,
+}
+
+/// Returns the middle page of an update, rejecting updates where it is ambiguous
+pub fn middle_page(update: &[i32]) -> Result<i32, UpdateError> {
+    match update.len() {
+        0 => Err(UpdateError::Empty),
+        len if len % 2 == 0 => Err(UpdateError::EvenLength(len)),
+        len => Ok(update[len / 2]),
+    }
+}
+
+/// Diagnose every update in the input, returning the ones that are invalid
+/// together with their index in the updates section
+pub fn diagnose(input: &str) -> Result<Vec<(usize, Diagnostic)>, ParseError> {
+    let (rules, updates) = parse(input)?;
+    let ordering = PageOrdering::new(&rules);
+
+    Ok(updates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, update)| Some((index, ordering.diagnose(update)?)))
+        .collect())
+}
+
+/// Settings for the Graphviz export of the rule graph
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions {
+    /// Draw rules broken by the update in red
+    pub highlight_violations: bool,
+    /// Number the pages in sorted order and draw the chain between them in bold blue
+    pub show_order: bool,
+}
+
+impl PageOrdering {
+    /// Export the rules as a Graphviz digraph with an edge `X -> Y` for each rule `X|Y`.
+    /// With an update, only its pages and the rules between them are drawn and the
+    /// options decide what is highlighted; a cycle among the pages is drawn in orange.
+    pub fn to_dot(&self, update: Option<&[i32]>, options: &DotOptions) -> String {
+        let pages: Vec<i32> = match update {
+            Some(update) => update.to_vec(),
+            None => self
+                .successors
+                .iter()
+                .flat_map(|(&p, qs)| std::iter::once(p).chain(qs.iter().copied()))
+                .collect::<BTreeSet<i32>>()
+                .into_iter()
+                .collect(),
+        };
+        let included: HashSet<i32> = pages.iter().copied().collect();
+
+        let mut edges: BTreeSet<Rule> = self
+            .successors
+            .iter()
+            .filter(|(p, _)| included.contains(p))
+            .flat_map(|(&p, qs)| {
+                qs.iter()
+                    .filter(|q| included.contains(q))
+                    .map(move |&q| (p, q))
+            })
+            .collect();
+
+        let mut violated = HashSet::new();
+        let mut order = Vec::new();
+        let mut cycle = HashSet::new();
+        if let Some(update) = update {
+            if options.highlight_violations {
+                violated.extend(self.violations(update).iter().map(|v| v.rule));
+            }
+            match self.sort(update) {
+                Ok(sorted) if options.show_order => order = sorted,
+                Ok(_) => {}
+                Err(err) => {
+                    let next = err.pages.iter().cycle().skip(1);
+                    cycle.extend(err.pages.iter().copied().zip(next.copied()));
+                }
+            }
+        }
+        let chain: HashSet<Rule> = order.windows(2).map(|w| (w[0], w[1])).collect();
+        // Consecutive pages without a rule between them still get a chain edge
+        edges.extend(chain.iter().copied());
+
+        let mut dot = String::from("digraph rules {\n    rankdir=LR;\n    node [shape=circle];\n");
+        for &page in &pages {
+            match order.iter().position(|&p| p == page) {
+                Some(rank) => dot.push_str(&format!(
+                    "    {} [label=\"{}\\n#{}\"];\n",
+                    page,
+                    page,
+                    rank + 1
+                )),
+                None => dot.push_str(&format!("    {};\n", page)),
+            }
+        }
 
-                if !(p_q && q_p) {
-                    return false;
+        for (p, q) in edges {
+            let mut attributes = Vec::new();
+            if violated.contains(&(p, q)) {
+                attributes.push("color=red");
+            } else if cycle.contains(&(p, q)) {
+                attributes.push("color=orange");
+            } else if chain.contains(&(p, q)) {
+                attributes.push("color=blue");
+            }
+            if chain.contains(&(p, q)) {
+                attributes.push("penwidth=2");
+                if !self.precedes(p, q) {
+                    attributes.push("style=dashed");
                 }
             }
+
+            if attributes.is_empty() {
+                dot.push_str(&format!("    {} -> {};\n", p, q));
+            } else {
+                dot.push_str(&format!(
+                    "    {} -> {} [{}];\n",
+                    p,
+                    q,
+                    attributes.join(", ")
+                ));
+            }
         }
-        true
-    };
 
-    // Partition updates
-    let mut correct: Vec<&UpdateList> = Vec::new();
-    let mut incorrect: Vec<&UpdateList> = Vec::new();
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Export the rule graph of the input as DOT, optionally limited to the update at
+/// `update` in the updates section. Returns None if there is no such update.
+pub fn rules_dot(
+    input: &str,
+    update: Option<usize>,
+    options: &DotOptions,
+) -> Result<Option<String>, ParseError> {
+    let (rules, updates) = parse(input)?;
+    let ordering = PageOrdering::new(&rules);
+
+    Ok(match update {
+        Some(index) => updates
+            .get(index)
+            .map(|update| ordering.to_dot(Some(update), options)),
+        None => Some(ordering.to_dot(None, options)),
+    })
+}
 
-    for update in updates {
-        if check_updates(update) {
-            correct.push(update);
-        } else {
-            incorrect.push(update);
+/// Sum the middle pages of the updates already in order, or report the index of the
+/// first such update without a middle page. Pages may be negative, and so may the sum.
+pub fn ordered_middles(
+    ordering: &PageOrdering,
+    updates: &[UpdateList],
+) -> Result<i64, (usize, UpdateError)> {
+    let mut sum = 0;
+    for (index, update) in updates.iter().enumerate() {
+        if ordering.is_ordered(update) {
+            sum += i64::from(middle_page(update).map_err(|err| (index, err))?);
         }
     }
+    Ok(sum)
+}
+
+/// Sum the middle pages of the out-of-order updates once reordered, or report the
+/// index of the first one that cannot be reordered or has no middle page
+pub fn reordered_middles(
+    ordering: &PageOrdering,
+    updates: &[UpdateList],
+) -> Result<i64, (usize, UpdateError)> {
+    let mut sum = 0;
+    for (index, update) in updates.iter().enumerate() {
+        if !ordering.is_ordered(update) {
+            let sorted = ordering
+                .sort(update)
+                .map_err(|err| (index, UpdateError::Cycle(err)))?;
+            sum += i64::from(middle_page(&sorted).map_err(|err| (index, err))?);
+        }
+    }
+    Ok(sum)
+}
 
-    (following, (correct, incorrect))
+/// Convert a sum of middle pages to an answer, rejecting a negative one at the updates
+fn answer(input: &str, sum: i64) -> Result<u64, ParseError> {
+    u64::try_from(sum).map_err(|_| {
+        let sections = sections(input);
+        let updates = &sections[1];
+        updates.error_at(
+            updates.text,
+            format!("middle pages sum to {}; the answer cannot be negative", sum),
+        )
+    })
 }
 
 /// Part 1: Process correct updates
 pub fn part1(input: &str) -> u64 {
-    let (rules, updates) = parse_input(input);
-    let (_, (correct_updates, _)) = split_updates(&rules, &updates);
-
-    let sum: i32 = correct_updates
-        .iter()
-        .map(|updates| updates[updates.len() / 2])
-        .sum();
-
-    sum as u64
+    let (rules, updates) = parse(input).unwrap_or_else(|err| panic!("{}", err));
+    let sum = ordered_middles(&PageOrdering::new(&rules), &updates)
+        .unwrap_or_else(|(_, err)| panic!("{}", err));
+    answer(input, sum).unwrap_or_else(|err| panic!("{}", err))
 }
 
 /// Part 2: Process incorrect updates
 pub fn part2(input: &str) -> u64 {
-    let (rules, updates) = parse_input(input);
-    let (following, (_, incorrect_updates)) = split_updates(&rules, &updates);
+    let (rules, updates) = parse(input).unwrap_or_else(|err| panic!("{}", err));
+    let sum = reordered_middles(&PageOrdering::new(&rules), &updates)
+        .unwrap_or_else(|(_, err)| panic!("{}", err));
+    answer(input, sum).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic
+/// with. An update without a middle page, or whose pages are caught in a cycle, is
+/// reported at its line, and a negative sum at the updates.
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    let (rules, updates) = parse(input)?;
+    let ordering = PageOrdering::new(&rules);
+
+    match (
+        ordered_middles(&ordering, &updates),
+        reordered_middles(&ordering, &updates),
+    ) {
+        (Ok(ordered), Ok(reordered)) => Ok((answer(input, ordered)?, answer(input, reordered)?)),
+        (Err(first), Ok(_)) | (Ok(_), Err(first)) => Err(update_error_at(input, first)),
+        (Err(a), Err(b)) => Err(update_error_at(input, if a.0 < b.0 { a } else { b })),
+    }
+}
+
+/// Place an error in the update at `index` on that update's line
+fn update_error_at(input: &str, (index, error): (usize, UpdateError)) -> ParseError {
+    let sections = sections(input);
+    let section = &sections[1];
+    let line = section
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .nth(index)
+        .expect("every parsed update has a line");
+    section.error_at(line, error.to_string())
+}
+
+/// Generate a complete rule set over 49 pages and `updates` updates of 5 to 23 distinct
+/// pages, always an odd number so that each has a middle page. About half are in order.
+pub fn generate(updates: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut pages: Vec<u32> = (10..100).collect();
+    rng.shuffle(&mut pages);
+    pages.truncate(49);
 
-    let sum: i32 = incorrect_updates
+    // `pages` is now the order that every rule agrees with
+    let mut rules: Vec<(u32, u32)> = pages
         .iter()
-        .map(|&updates| {
-            // Create a sorted version based on the following relationship
-            let mut sorted_updates = updates.clone();
-            sorted_updates.sort_by(|&p, &q| match following.get(&p) {
-                Some(v) if v.contains(&q) => std::cmp::Ordering::Less,
-                _ => std::cmp::Ordering::Greater,
-            });
-
-            sorted_updates[sorted_updates.len() / 2]
-        })
-        .sum();
+        .enumerate()
+        .flat_map(|(i, &before)| pages[i + 1..].iter().map(move |&after| (before, after)))
+        .collect();
+    rng.shuffle(&mut rules);
+
+    let mut input = String::new();
+    for (before, after) in rules {
+        writeln!(input, "{}|{}", before, after).unwrap();
+    }
+    input.push('\n');
+
+    for _ in 0..updates {
+        let mut ranks: Vec<usize> = (0..pages.len()).collect();
+        rng.shuffle(&mut ranks);
+        ranks.truncate(2 * rng.index(10) + 5);
+        if rng.chance(0.5) {
+            ranks.sort_unstable();
+        }
+
+        let update: Vec<String> = ranks.iter().map(|&rank| pages[rank].to_string()).collect();
+        input.push_str(&update.join(","));
+        input.push('\n');
+    }
 
-    sum as u64
+    input
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_common::metamorphic::{self, Transform};
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "47|53
 97|13
@@ -185,4 +756,377 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 123);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("47|53\n47-53\n\n75,47,61").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 1, 6..11));
+        assert_eq!(err.message, "expected '|' after the page number");
+
+        let err = parse("47|5a\n\n75,47,61").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 4));
+
+        let err = parse("47|53\n\n75, x,61").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (3, 5, 11..12));
+        assert_eq!(err.to_string(), "line 3, column 5: invalid page number 'x'");
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        // Rules only: the updates section may be missing
+        assert_eq!(
+            parse("\n47|53\n97|13\n\n").unwrap(),
+            (vec![(47, 53), (97, 13)], vec![])
+        );
+
+        let err = parse("47|53\n75,47,61\n").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 1, 6..14));
+        assert_eq!(
+            err.message,
+            "update found among the rules; updates must follow a blank line"
+        );
+
+        let err = parse("47|53\n\n\n75,47\n61|13").unwrap_err();
+        assert_eq!((err.line, err.column), (5, 1));
+        assert!(err.message.starts_with("rule found among the updates"));
+
+        let err = parse("47|53\n\n75,47\n\n61,13").unwrap_err();
+        assert_eq!((err.line, err.span.clone()), (5, 14..19));
+        assert_eq!(
+            err.message,
+            "expected at most 2 sections separated by blank lines, found more"
+        );
+
+        assert_eq!(
+            parse(" \n").unwrap_err().message,
+            "expected 1 section separated by blank lines, found 0"
+        );
+        // A rule with a comma in it is reported against the rules, not taken as an update
+        assert_eq!(
+            parse("47|5,3\n\n75,47").unwrap_err().message,
+            "invalid page number '5,3'"
+        );
+    }
+
+    #[test]
+    fn test_sort_partial_rules() {
+        // No rule relates 1 and 2, so they keep their original order
+        let ordering = PageOrdering::new(&[(3, 1)]);
+        assert!(!ordering.is_ordered(&[1, 2, 3]));
+        assert_eq!(ordering.sort(&[1, 2, 3]), Ok(vec![2, 3, 1]));
+        assert!(ordering.is_ordered(&[2, 3, 1]));
+        assert!(ordering.is_ordered(&[5, 6]));
+    }
+
+    #[test]
+    fn test_sort_reports_cycle() {
+        let ordering = PageOrdering::new(&[(1, 2), (2, 3), (3, 1), (4, 1)]);
+        let err = ordering.sort(&[4, 1, 2, 3]).unwrap_err();
+        assert_eq!(err.pages.len(), 3);
+        let start = err.pages.iter().position(|&p| p == 1).unwrap();
+        assert!(err.pages.iter().cycle().skip(start).take(3).eq(&[1, 2, 3]));
+        assert!(
+            err.to_string()
+                .starts_with("ordering rules contain a cycle: ")
+        );
+    }
+
+    #[test]
+    fn test_diagnose_example() {
+        let diagnostics = diagnose(EXAMPLE_INPUT).unwrap();
+        let indices: Vec<usize> = diagnostics.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![3, 4, 5]);
+
+        let (_, diagnostic) = &diagnostics[0];
+        assert_eq!(diagnostic.update, vec![75, 97, 47, 61, 53]);
+        assert_eq!(
+            diagnostic.violations,
+            vec![Violation {
+                rule: (97, 75),
+                before_position: 1,
+                after_position: 0,
+            }]
+        );
+        assert_eq!(diagnostic.reordered, Some(vec![97, 75, 47, 61, 53]));
+        assert_eq!(diagnostic.moved.len(), 1);
+        assert!(diagnostic.errors.is_empty());
+        assert_eq!(
+            diagnostic.violations[0].to_string(),
+            "97|75 broken: 75 at position 0 is printed before 97 at position 1"
+        );
+
+        let (_, diagnostic) = &diagnostics[2];
+        assert_eq!(diagnostic.reordered, Some(vec![97, 75, 47, 29, 13]));
+        assert_eq!(diagnostic.moved.len(), 2);
+    }
+
+    #[test]
+    fn test_fewest_moved_pages() {
+        // Sorting moves 1 and 2 in front of 3 and 4, but moving 2 alone is enough
+        let ordering = PageOrdering::new(&[(2, 4), (2, 3)]);
+        let diagnostic = ordering.diagnose(&[3, 4, 1, 2, 5]).unwrap();
+        assert_eq!(diagnostic.reordered, Some(vec![1, 2, 3, 4, 5]));
+        assert_eq!(diagnostic.moved, vec![2]);
+
+        // 3 must follow 1 through 2, so keeping 3 before 1 is not an option
+        let ordering = PageOrdering::new(&[(1, 2), (2, 3)]);
+        let diagnostic = ordering.diagnose(&[3, 1, 2]).unwrap();
+        assert_eq!(diagnostic.moved, vec![3]);
+    }
+
+    #[test]
+    fn test_even_length_update() {
+        let ordering = PageOrdering::new(&[(1, 2)]);
+        let diagnostic = ordering.diagnose(&[1, 2]).unwrap();
+        assert!(diagnostic.violations.is_empty());
+        assert_eq!(diagnostic.errors, vec![UpdateError::EvenLength(2)]);
+        assert_eq!(middle_page(&[]), Err(UpdateError::Empty));
+        assert_eq!(middle_page(&[1, 2, 3]), Ok(2));
+        assert_eq!(ordering.diagnose(&[1, 3, 2]), None);
+    }
+
+    #[test]
+    fn test_answers_reject_unsolvable_updates() {
+        assert_eq!(answers(EXAMPLE_INPUT), Ok((143, 123)));
+
+        let err = answers("1|2\n\n1,2,3\n1,2").unwrap_err();
+        assert_eq!((err.line, err.span.clone()), (4, 11..14));
+        assert_eq!(
+            err.message,
+            "update has 2 pages, so the middle page is ambiguous"
+        );
+
+        let err = answers("1|2\n2|3\n3|1\n\n3,2,1").unwrap_err();
+        assert_eq!(err.line, 5);
+        assert!(err.message.starts_with("ordering rules contain a cycle: "));
+
+        // Negative pages are read, but an answer made of them cannot be reported
+        let input = "-5|3\n\n-5,3,-7\n1,-7,3\n";
+        let err = answers(input).unwrap_err();
+        assert_eq!((err.line, err.span.clone()), (3, 6..20));
+        assert_eq!(
+            err.message,
+            "middle pages sum to -4; the answer cannot be negative"
+        );
+        assert_eq!(answers("-5|3\n\n-5,3,7\n3,-5,1\n"), Ok((3, 3)));
+    }
+
+    /// Compare against a file in `testdata/`, rewriting it when UPDATE_GOLDEN is set
+    fn assert_golden(name: &str, actual: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        assert_eq!(actual, expected, "golden file {} is out of date", name);
+    }
+
+    #[test]
+    fn test_dot_golden() {
+        let plain = DotOptions::default();
+        let highlighted = DotOptions {
+            highlight_violations: true,
+            show_order: true,
+        };
+
+        assert_golden(
+            "rules.dot",
+            &rules_dot(EXAMPLE_INPUT, None, &plain).unwrap().unwrap(),
+        );
+        assert_golden(
+            "update3.dot",
+            &rules_dot(EXAMPLE_INPUT, Some(3), &highlighted)
+                .unwrap()
+                .unwrap(),
+        );
+        assert_golden(
+            "update5.dot",
+            &rules_dot(EXAMPLE_INPUT, Some(5), &highlighted)
+                .unwrap()
+                .unwrap(),
+        );
+        assert_eq!(rules_dot(EXAMPLE_INPUT, Some(6), &plain), Ok(None));
+    }
+
+    #[test]
+    fn test_dot_cycle_and_missing_rules() {
+        let ordering = PageOrdering::new(&[(1, 2), (2, 3), (3, 1), (5, 6)]);
+        let dot = ordering.to_dot(Some(&[1, 2, 3]), &DotOptions::default());
+        assert!(dot.contains("    1 -> 2 [color=orange];\n"));
+        assert!(dot.contains("    3 -> 1 [color=orange];\n"));
+        assert!(!dot.contains('5'));
+
+        // 4 and 6 are not related by a rule, so the chain between them is dashed
+        let options = DotOptions {
+            show_order: true,
+            ..DotOptions::default()
+        };
+        let dot = ordering.to_dot(Some(&[5, 4, 6]), &options);
+        assert!(dot.contains("    4 -> 6 [color=blue, penwidth=2, style=dashed];\n"));
+        assert!(dot.contains("    5 -> 4 [color=blue, penwidth=2, style=dashed];\n"));
+    }
+
+    /// A full set of rules for a random page order, listed in random order, and
+    /// updates of an odd number of distinct pages
+    fn queue_strategy() -> impl Strategy<Value = (Vec<Rule>, Vec<UpdateList>)> {
+        prop::sample::subsequence((10..100).collect::<Vec<i32>>(), 2..12)
+            .prop_shuffle()
+            .prop_flat_map(|order| {
+                let rules: Vec<Rule> = order
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, &a)| order[i + 1..].iter().map(move |&b| (a, b)))
+                    .collect();
+                let update = prop::sample::subsequence(order.clone(), 1..=order.len())
+                    .prop_shuffle()
+                    .prop_map(|mut pages| {
+                        if pages.len() % 2 == 0 {
+                            pages.pop();
+                        }
+                        pages
+                    });
+                (
+                    Just(rules).prop_shuffle(),
+                    prop::collection::vec(update, 0..8),
+                )
+            })
+    }
+
+    /// Naive reference: an update is in order if no rule is broken by any pair of its
+    /// pages; a page's place in the fixed order is the number of pages that must precede it
+    /// Every order of the update's pages that follows the rules
+    fn valid_orders(ordering: &PageOrdering, update: &[i32]) -> Vec<UpdateList> {
+        if update.is_empty() {
+            return vec![Vec::new()];
+        }
+        let mut orders = Vec::new();
+        for (i, &page) in update.iter().enumerate() {
+            let mut rest = update.to_vec();
+            rest.remove(i);
+            for mut order in valid_orders(ordering, &rest) {
+                order.insert(0, page);
+                if ordering.is_ordered(&order) {
+                    orders.push(order);
+                }
+            }
+        }
+        orders
+    }
+
+    fn is_subsequence(short: &[i32], long: &[i32]) -> bool {
+        let mut long = long.iter();
+        short.iter().all(|page| long.any(|other| other == page))
+    }
+
+    fn naive(rules: &[Rule], updates: &[UpdateList]) -> (u64, u64) {
+        let (mut correct, mut fixed) = (0, 0);
+        for update in updates {
+            let in_order = (0..update.len())
+                .all(|i| (i + 1..update.len()).all(|j| !rules.contains(&(update[j], update[i]))));
+            let middle = if in_order {
+                update[update.len() / 2]
+            } else {
+                *update
+                    .iter()
+                    .find(|&&page| {
+                        update
+                            .iter()
+                            .filter(|&&other| rules.contains(&(other, page)))
+                            .count()
+                            == update.len() / 2
+                    })
+                    .unwrap()
+            };
+            if in_order {
+                correct += middle as u64;
+            } else {
+                fixed += middle as u64;
+            }
+        }
+        (correct, fixed)
+    }
+
+    #[test]
+    fn test_metamorphic_example() {
+        // Neither the order of the rules nor the order of the updates matters
+        let transforms = metamorphic::reseeded(Transform::ShuffleSections, 0, 4);
+        metamorphic::check_both(part1, part2, EXAMPLE_INPUT, &transforms).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn parts_match_naive((rules, updates) in queue_strategy()) {
+            let mut input = String::new();
+            for (before, after) in &rules {
+                input.push_str(&format!("{}|{}\n", before, after));
+            }
+            input.push('\n');
+            for update in &updates {
+                let pages: Vec<String> = update.iter().map(i32::to_string).collect();
+                input.push_str(&pages.join(","));
+                input.push('\n');
+            }
+
+            prop_assert_eq!((part1(&input), part2(&input)), naive(&rules, &updates));
+        }
+
+        #[test]
+        fn moved_pages_are_fewest(
+            rules in prop::collection::vec((1..6).prop_flat_map(|p| (Just(p), p + 1..7)), 0..12),
+            update in prop::sample::subsequence((1..7).collect::<Vec<i32>>(), 1..=6).prop_shuffle(),
+        ) {
+            let ordering = PageOrdering::new(&rules);
+            let orders = valid_orders(&ordering, &update);
+            let fits = |kept: &[i32]| orders.iter().any(|order| is_subsequence(kept, order));
+            let most_kept = (0..1u32 << update.len())
+                .map(|mask| {
+                    (0..update.len())
+                        .filter(|&i| mask >> i & 1 == 1)
+                        .map(|i| update[i])
+                        .collect::<Vec<i32>>()
+                })
+                .filter(|kept| fits(kept))
+                .map(|kept| kept.len())
+                .max();
+
+            let moved = ordering.diagnose(&update).map_or(Vec::new(), |d| d.moved);
+            let kept: Vec<i32> = update.iter().copied().filter(|p| !moved.contains(p)).collect();
+            prop_assert!(fits(&kept));
+            prop_assert_eq!(Some(kept.len()), most_kept);
+        }
+
+        #[test]
+        fn fast_paths_agree_with_derived(line in "[-+0-9,|x]{0,12}") {
+            if let Some((before, after)) = quick_rule(&line) {
+                prop_assert_eq!(line.parse::<RuleLine>(), Ok(RuleLine { before, after }));
+            }
+            if let Some(pages) = split_ints(&line, b',') {
+                prop_assert_eq!(line.parse::<UpdateLine>(), Ok(UpdateLine { pages }));
+            }
+        }
+
+        #[test]
+        fn generated_inputs_match_naive(count in 0usize..12, seed in any::<u64>()) {
+            let input = generate(count, seed);
+            let (rules, updates) = parse(&input).unwrap();
+            prop_assert_eq!((rules.len(), updates.len()), (49 * 48 / 2, count));
+            prop_assert_eq!((part1(&input), part2(&input)), naive(&rules, &updates));
+        }
+    }
+
+    proptest! {
+        // Each case parses every rule of a full page set five times
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn generated_inputs_are_invariant(updates in 0usize..12, seed in any::<u64>()) {
+            let transforms = metamorphic::reseeded(Transform::ShuffleSections, seed, 4);
+            let input = generate(updates, seed);
+            let checked = metamorphic::check_both(part1, part2, &input, &transforms);
+            prop_assert_eq!(checked, Ok(()));
+        }
+    }
 }
@@ -1,8 +1,9 @@
-use day05::{part1, part2};
+use day05::answers;
 use std::io::{self, Read};
+use std::process::ExitCode;
 use std::time::Instant;
 
-fn main() {
+fn main() -> ExitCode {
     // Read input from stdin
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
@@ -10,9 +11,16 @@ fn main() {
 
     let start_time = Instant::now();
 
-    println!("Part 1: {}", part1(input));
-    println!("Part 2: {}", part2(input));
+    // Updates without a middle page, or caught in a cycle, are reported like parse errors
+    let (part1, part2) = match answers(input) {
+        Ok(answers) => answers,
+        Err(err) => return aoc_common::parse::report(&err, input, "<stdin>"),
+    };
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
 
     let elapsed = start_time.elapsed();
     println!("Elapsed time: {:.4} seconds", elapsed.as_secs_f64());
+
+    ExitCode::SUCCESS
 }
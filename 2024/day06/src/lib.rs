@@ -1,6 +1,10 @@
+use aoc_common::rng::Rng;
+use aoc_common::{Grid, ParseError, Simulation};
+use std::collections::HashSet;
+
 /// Direction represents possible movements in the grid.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
+pub enum Direction {
     Up,    // Move upward
     Right, // Move rightward
     Down,  // Move downward
@@ -17,208 +21,293 @@ impl Direction {
             Direction::Left => Direction::Up,
         }
     }
+
+    /// Glyph used to draw a guard facing this way
+    pub fn glyph(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
 }
 
-/// Parse input string to 2D grid
-fn parse_grid(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .filter(|s| !s.trim().is_empty())
-        .map(|row| row.chars().collect())
-        .collect()
+/// The lab map as rows of glyphs
+type Map = Vec<Vec<char>>;
+
+/// Parse the map and locate the guard's starting position marked with '^'
+fn parse_map(input: &str) -> Result<(Map, (usize, usize)), ParseError> {
+    let map = aoc_common::grid::parse_rows(input)?;
+
+    let start = map
+        .iter()
+        .enumerate()
+        .find_map(|(i, row)| Some((i, row.iter().position(|&c| c == '^')?)))
+        .ok_or_else(|| {
+            ParseError::new(
+                input,
+                input.len()..input.len(),
+                "no starting position '^' found",
+            )
+        })?;
+
+    Ok((map, start))
 }
 
-pub fn part1(input: &str) -> u64 {
-    let map = parse_grid(input);
-    let n = map.len();
-
-    // Verify all rows have the same length
-    if map.iter().any(|row| row.len() != n) {
-        panic!("Invalid map: not all rows have the same length");
-    }
-
-    // Find a starting position marked with '^'
-    let (start_i, start_j) = (0..n)
-        .flat_map(|i| (0..n).map(move |j| (i, j)))
-        .find(|&(i, j)| map[i][j] == '^')
-        .expect("No starting position found");
-
-    // Start simulation with the initial position and Up direction
-    let mut i = start_i;
-    let mut j = start_j;
-    let mut dir = Direction::Up;
-    let mut history = vec![(start_i, start_j)];
-
-    loop {
-        // Compute the next position based on the current direction
-        let next_pos = match dir {
-            Direction::Up => {
-                if i >= 1 {
-                    Some((i - 1, j))
-                } else {
-                    None
-                }
-            }
-            Direction::Right => {
-                if j < n - 1 {
-                    Some((i, j + 1))
-                } else {
-                    None
-                }
-            }
-            Direction::Down => {
-                if i < n - 1 {
-                    Some((i + 1, j))
-                } else {
-                    None
-                }
-            }
-            Direction::Left => {
-                if j >= 1 {
-                    Some((i, j - 1))
-                } else {
-                    None
-                }
-            }
+/// Compute the next position based on the current direction, or None if it leaves
+/// a map of `rows` by `cols` cells
+fn next_position(
+    (rows, cols): (usize, usize),
+    (i, j): (usize, usize),
+    dir: Direction,
+) -> Option<(usize, usize)> {
+    match dir {
+        Direction::Up if i >= 1 => Some((i - 1, j)),
+        Direction::Right if j + 1 < cols => Some((i, j + 1)),
+        Direction::Down if i + 1 < rows => Some((i + 1, j)),
+        Direction::Left if j >= 1 => Some((i, j - 1)),
+        _ => None,
+    }
+}
+
+/// Number of rows and columns of a map
+fn dimensions(map: &[Vec<char>]) -> (usize, usize) {
+    (map.len(), map.first().map_or(0, Vec::len))
+}
+
+/// Where the guard stands and which way it is facing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GuardState {
+    pub position: (usize, usize),
+    pub direction: Direction,
+}
+
+/// The guard's patrol, advanced one move or turn at a time.
+/// Yields each new state and ends when the guard is about to leave the map
+/// or has just repeated a state it was in before.
+#[derive(Debug, Clone)]
+pub struct Walk {
+    map: Vec<Vec<char>>,
+    state: GuardState,
+    seen: HashSet<GuardState>,
+    visited: HashSet<(usize, usize)>,
+    looped: bool,
+}
+
+impl Walk {
+    pub fn new(input: &str) -> Result<Self, ParseError> {
+        let (map, start) = parse_map(input)?;
+        Ok(Self::on_map(map, start))
+    }
+
+    /// Start a walk on a map that is already parsed
+    fn on_map(map: Vec<Vec<char>>, start: (usize, usize)) -> Self {
+        let state = GuardState {
+            position: start,
+            direction: Direction::Up,
         };
 
-        if let Some((ni, nj)) = next_pos {
-            if map[ni][nj] == '#' {
-                // Hit the wall - stay in place but rotate
-                dir = dir.next_direction();
-            } else {
-                // Move forward + track position
-                i = ni;
-                j = nj;
-                history.push((ni, nj));
-            }
-        }
-        // Out of bounds: stay put (implicit else case)
-
-        // Check if we're stuck (same position and direction after a move)
-        if next_pos.is_none()
-            || (next_pos.is_some() && map[next_pos.unwrap().0][next_pos.unwrap().1] == '#')
-        {
-            let stuck_condition = match next_pos {
-                Some((ni, nj)) => ni == i && nj == j,
-                None => true,
-            };
-
-            if stuck_condition {
-                // Count unique positions
-                let mut unique_positions = history.clone();
-                unique_positions.sort();
-                unique_positions.dedup();
-                return unique_positions.len() as u64;
-            }
+        Walk {
+            map,
+            state,
+            seen: HashSet::from([state]),
+            visited: HashSet::from([start]),
+            looped: false,
         }
     }
+
+    /// The guard's current state
+    pub fn state(&self) -> GuardState {
+        self.state
+    }
+
+    /// Whether the walk ended because the guard started going round in a loop
+    pub fn looped(&self) -> bool {
+        self.looped
+    }
+
+    /// Distinct positions the guard has stood on so far
+    pub fn visited(&self) -> usize {
+        self.visited.len()
+    }
 }
 
-pub fn part2(input: &str) -> u64 {
-    let map = parse_grid(input);
-    let n = map.len();
-
-    // Verify all rows have the same length
-    if map.iter().any(|row| row.len() != n) {
-        panic!("Invalid map: not all rows have the same length");
-    }
-
-    // Find a starting position marked with '^'
-    let (start_i, start_j) = (0..n)
-        .flat_map(|i| (0..n).map(move |j| (i, j)))
-        .find(|&(i, j)| map[i][j] == '^')
-        .expect("No starting position found");
-
-    // Function to check if the blocking position creates a loop
-    fn find_loop(map: &[Vec<char>], start_i: usize, start_j: usize, start_dir: Direction) -> bool {
-        use std::collections::{HashSet, VecDeque};
-
-        let n = map.len();
-        let mut history = HashSet::new();
-        let mut queue = VecDeque::new();
-        queue.push_back((start_i, start_j, start_dir));
-
-        while !queue.is_empty() {
-            let (i, j, dir) = queue.pop_front().unwrap();
-
-            // Compute the next position based on the current direction
-            let next_pos = match dir {
-                Direction::Up => {
-                    if i >= 1 {
-                        Some((i - 1, j))
-                    } else {
-                        None
-                    }
-                }
-                Direction::Right => {
-                    if j + 1 < n {
-                        Some((i, j + 1))
-                    } else {
-                        None
-                    }
-                }
-                Direction::Down => {
-                    if i + 1 < n {
-                        Some((i + 1, j))
-                    } else {
-                        None
-                    }
-                }
-                Direction::Left => {
-                    if j >= 1 {
-                        Some((i, j - 1))
-                    } else {
-                        None
-                    }
-                }
-            };
-
-            if let Some((ni, nj)) = next_pos {
-                let (ni, nj, nd) = if map[ni][nj] == '#' {
-                    (i, j, dir.next_direction())
-                } else {
-                    (ni, nj, dir)
-                };
-
-                let state = (ni, nj, nd);
-                if history.contains(&state) {
-                    return true; // Loop found
-                } else {
-                    history.insert(state);
-                    queue.push_back(state);
-                }
+impl Iterator for Walk {
+    type Item = GuardState;
+
+    fn next(&mut self) -> Option<GuardState> {
+        if self.looped {
+            return None;
+        }
+
+        let GuardState {
+            position,
+            direction,
+        } = self.state;
+        let (ni, nj) = next_position(dimensions(&self.map), position, direction)?;
+
+        self.state = if self.map[ni][nj] == '#' {
+            // Hit the wall - stay in place but rotate
+            GuardState {
+                position,
+                direction: direction.next_direction(),
             }
+        } else {
+            self.visited.insert((ni, nj));
+            GuardState {
+                position: (ni, nj),
+                direction,
+            }
+        };
+        self.looped = !self.seen.insert(self.state);
+
+        Some(self.state)
+    }
+}
+
+impl Simulation for Walk {
+    fn step(&mut self) -> bool {
+        self.next().is_some()
+    }
+
+    /// The map with visited cells marked 'X' and the guard drawn facing its direction
+    fn frame(&self) -> Grid<char> {
+        let mut rows = self.map.clone();
+        for &(i, j) in &self.visited {
+            rows[i][j] = 'X';
         }
+        let (i, j) = self.state.position;
+        rows[i][j] = self.state.direction.glyph();
+
+        Grid::from_rows(rows).expect("map rows have equal length")
+    }
 
-        false
+    fn focus(&self) -> Option<(usize, usize)> {
+        Some(self.state.position)
     }
+}
+
+/// Positions the guard stands on, in order, until leaving the map.
+/// Stops early if the guard starts repeating itself.
+pub fn guard_path(input: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+    Ok(path_of(Walk::new(input)?))
+}
+
+/// Every position of a walk in order, including the start
+fn path_of(walk: Walk) -> Vec<(usize, usize)> {
+    let start = walk.state().position;
+
+    // Turning leaves the guard in place, so consecutive duplicates are turns
+    let mut path: Vec<(usize, usize)> = std::iter::once(start)
+        .chain(walk.map(|state| state.position))
+        .collect();
+    path.dedup();
+    path
+}
+
+/// Function to check if the guard walks in a loop
+fn find_loop(map: &[Vec<char>], start_i: usize, start_j: usize, start_dir: Direction) -> bool {
+    let size = dimensions(map);
+    let mut history = HashSet::new();
+    let mut state = (start_i, start_j, start_dir);
+
+    while let Some((ni, nj)) = next_position(size, (state.0, state.1), state.2) {
+        let (i, j, dir) = state;
+        state = if map[ni][nj] == '#' {
+            (i, j, dir.next_direction())
+        } else {
+            (ni, nj, dir)
+        };
+
+        if !history.insert(state) {
+            return true; // Loop found
+        }
+    }
+
+    false
+}
+
+/// Positions where a single new obstruction traps the guard in a loop
+pub fn loop_obstacles(input: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+    let (map, start) = parse_map(input)?;
+    Ok(obstacles_on(&map, start))
+}
+
+/// [`loop_obstacles`] on a map that is already parsed
+fn obstacles_on(map: &[Vec<char>], (start_i, start_j): (usize, usize)) -> Vec<(usize, usize)> {
+    let (rows, cols) = dimensions(map);
 
     // Instead of using threads directly, we'll use a regular loop
     // This part could be optimized with rayon for parallelism if needed
-    let mut loop_count = 0;
+    let mut obstacles = Vec::new();
+    let mut new_map = map.to_vec();
 
-    for i in 0..n {
-        for j in 0..n {
+    for i in 0..rows {
+        for j in 0..cols {
             if map[i][j] == '.' {
-                // Create a new map with a wall at (i, j)
-                let mut new_map = map.clone();
+                // Place a wall at (i, j) and check if this creates a loop
                 new_map[i][j] = '#';
-
-                // Check if this creates a loop
                 if find_loop(&new_map, start_i, start_j, Direction::Up) {
-                    loop_count += 1;
+                    obstacles.push((i, j));
                 }
+                new_map[i][j] = '.';
             }
         }
     }
 
-    loop_count
+    obstacles
+}
+
+pub fn part1(input: &str) -> u64 {
+    distinct_positions(guard_path(input).unwrap_or_else(|err| panic!("{}", err)))
+}
+
+/// Count the unique positions on a path
+fn distinct_positions(mut path: Vec<(usize, usize)>) -> u64 {
+    path.sort();
+    path.dedup();
+    path.len() as u64
+}
+
+pub fn part2(input: &str) -> u64 {
+    loop_obstacles(input)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .len() as u64
+}
+
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic with
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    let (map, start) = parse_map(input)?;
+    let obstacles = obstacles_on(&map, start).len() as u64;
+    let path = path_of(Walk::on_map(map, start));
+    Ok((distinct_positions(path), obstacles))
+}
+
+/// Generate a `size` by `size` lab with obstructions on about 5% of the floor, as in a
+/// real input, and the guard facing up from a random cell
+pub fn generate(size: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut map: Vec<Vec<char>> = (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| if rng.chance(0.05) { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    if size > 0 {
+        map[rng.index(size)][rng.index(size)] = '^';
+    }
+
+    map.into_iter()
+        .map(|row| row.into_iter().collect::<String>() + "\n")
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "....#.....
 .........#
@@ -240,4 +329,133 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 6);
     }
+
+    #[test]
+    fn test_guard_path_and_obstacles() {
+        let path = guard_path(EXAMPLE_INPUT).unwrap();
+        assert_eq!(path.first(), Some(&(6, 4)));
+        assert_eq!(path.last(), Some(&(9, 7)));
+        assert_eq!(path.len(), 45);
+
+        let obstacles = loop_obstacles(EXAMPLE_INPUT).unwrap();
+        assert_eq!(obstacles[0], (6, 3));
+        assert!(obstacles.contains(&(9, 7)));
+    }
+
+    #[test]
+    fn test_walk_states() {
+        let mut walk = Walk::new(EXAMPLE_INPUT).unwrap();
+        assert_eq!(walk.next().map(|s| s.position), Some((5, 4)));
+
+        // Four more moves up, then the wall turns the guard right in place
+        let turn = walk.nth(4).unwrap();
+        assert_eq!(turn.position, (1, 4));
+        assert_eq!(turn.direction, Direction::Right);
+
+        let last = walk.by_ref().last().unwrap();
+        assert_eq!(last.position, (9, 7));
+        assert!(!walk.looped());
+        assert_eq!(walk.visited(), 41);
+    }
+
+    #[test]
+    fn test_walk_frame() {
+        let mut walk = Walk::new(EXAMPLE_INPUT).unwrap();
+        walk.step();
+        walk.step();
+
+        let frame = walk.frame();
+        assert_eq!(frame.get(4, 4), Some(&'^'));
+        assert_eq!(frame.get(5, 4), Some(&'X'));
+        assert_eq!(frame.get(6, 4), Some(&'X'));
+        assert_eq!(frame.get(0, 4), Some(&'#'));
+        assert_eq!(walk.focus(), Some((4, 4)));
+    }
+
+    #[test]
+    fn test_rectangular_and_invalid_maps() {
+        // Wider than tall: the guard turns at the wall and walks off the right edge
+        assert_eq!(guard_path("..#...\n..^...").unwrap().len(), 4);
+
+        let err = guard_path("....\n..^\n....").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+        assert_eq!(err.message, "row has 3 columns, expected 4");
+
+        let err = Walk::new("...\n.#.").unwrap_err();
+        assert_eq!(err.message, "no starting position '^' found");
+    }
+
+    /// Naive reference: walk the guard cell by cell, returning the cells visited and
+    /// whether it ended up going round in a loop
+    fn simulate(map: &[Vec<char>], start: (usize, usize)) -> (HashSet<(usize, usize)>, bool) {
+        let (rows, cols) = (map.len() as isize, map[0].len() as isize);
+        let (mut r, mut c) = (start.0 as isize, start.1 as isize);
+        let (mut dr, mut dc) = (-1, 0);
+        let mut visited = HashSet::new();
+        let mut states = HashSet::new();
+
+        loop {
+            if !states.insert((r, c, dr, dc)) {
+                return (visited, true);
+            }
+            visited.insert((r as usize, c as usize));
+            let (nr, nc) = (r + dr, c + dc);
+            if nr < 0 || nr >= rows || nc < 0 || nc >= cols {
+                return (visited, false);
+            }
+            if map[nr as usize][nc as usize] == '#' {
+                // Turn right
+                (dr, dc) = (dc, -dr);
+            } else {
+                (r, c) = (nr, nc);
+            }
+        }
+    }
+
+    /// Small maps with scattered obstacles and the guard somewhere on an empty cell
+    fn map_strategy() -> impl Strategy<Value = (Vec<Vec<char>>, (usize, usize))> {
+        (1usize..10, 1usize..10).prop_flat_map(|(rows, cols)| {
+            let cell = prop::sample::select(vec!['.', '.', '.', '.', '#']);
+            (
+                prop::collection::vec(prop::collection::vec(cell, cols), rows),
+                0..rows,
+                0..cols,
+            )
+                .prop_map(|(mut map, r, c)| {
+                    map[r][c] = '^';
+                    (map, (r, c))
+                })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn parts_match_simulation((map, start) in map_strategy()) {
+            let input: String = map.iter().map(|row| row.iter().collect::<String>() + "\n").collect();
+
+            let (visited, _) = simulate(&map, start);
+            prop_assert_eq!(part1(&input), visited.len() as u64);
+
+            let mut loops = 0;
+            let mut blocked = map.clone();
+            for r in 0..map.len() {
+                for c in 0..map[r].len() {
+                    if map[r][c] == '.' {
+                        blocked[r][c] = '#';
+                        loops += u64::from(simulate(&blocked, start).1);
+                        blocked[r][c] = '.';
+                    }
+                }
+            }
+            prop_assert_eq!(part2(&input), loops);
+        }
+
+        #[test]
+        fn generated_maps_match_simulation(size in 1usize..14, seed in any::<u64>()) {
+            let input = generate(size, seed);
+            let map: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+            let start = Walk::new(&input).unwrap().state().position;
+            prop_assert_eq!(part1(&input), simulate(&map, start).0.len() as u64);
+        }
+    }
 }
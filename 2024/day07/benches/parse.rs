@@ -0,0 +1,38 @@
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::path::Path;
+
+/// The real puzzle input when it has been downloaded, else 850 generated equations
+fn load_input() -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../inputs/2024/day07.txt");
+    std::fs::read_to_string(path).unwrap_or_else(|_| day07::generate(850, 2024))
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let input = load_input();
+    let mut group = c.benchmark_group("day07_parse");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    // The previous approach: str::parse on the target and every whitespace-separated operand
+    group.bench_function("str_parse", |b| {
+        b.iter(|| {
+            black_box(&input)
+                .lines()
+                .map(|line| {
+                    let (target, operands) = line.split_once(':').unwrap();
+                    let operands: Vec<u64> = operands
+                        .split_whitespace()
+                        .map(|n| n.parse().unwrap())
+                        .collect();
+                    (target.parse::<u64>().unwrap(), operands)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| day07::parse(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use day07::{PART1_OPERATORS, PART2_OPERATORS, is_solvable, parse};
+use std::path::Path;
+
+/// The real puzzle input when it has been downloaded, else 850 generated equations
+fn load_input() -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../inputs/2024/day07.txt");
+    std::fs::read_to_string(path).unwrap_or_else(|_| day07::generate(850, 2024))
+}
+
+fn bench_search(c: &mut Criterion) {
+    let equations = parse(&load_input()).unwrap();
+    let mut group = c.benchmark_group("day07_search");
+
+    for (name, operators) in [("part1", PART1_OPERATORS), ("part2", PART2_OPERATORS)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                equations
+                    .iter()
+                    .filter(|(target, operands)| is_solvable(*target, operands, operators))
+                    .count()
+            })
+        });
+    }
+
+    // Twelve operands, the most a real input has, and a target none of them reach
+    let operands = vec![7; 12];
+    group.bench_function("unsolvable_12", |b| {
+        b.iter(|| is_solvable(black_box(1_000_003), &operands, PART2_OPERATORS))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -1,100 +1,441 @@
+use aoc_common::format::parse_lines_by;
+use aoc_common::ints::{parse_int, uints};
+use aoc_common::rng::Rng;
+use aoc_common::{AocParse, ParseError, ParseMode};
+use std::fmt;
+use std::fmt::Write as _;
+use std::ops::Range;
+
+/// One line of the input: a test value and the operands that should produce it
+#[derive(Debug, Clone, PartialEq, Eq, AocParse)]
+#[aoc(format = "{target: label='test value'}: {operands: sep=' ', min=1, label='operand'}")]
+struct Equation {
+    target: u64,
+    operands: Vec<u64>,
+}
+
+/// Parse one line, falling back to the derived parser to explain a malformed one
+fn parse_equation(line: &str) -> Result<Equation, ParseError> {
+    quick_equation(line).map_or_else(|| line.parse(), Ok)
+}
+
+/// Parse a well-formed line without building errors, or None to leave it to the derived parser
+fn quick_equation(line: &str) -> Option<Equation> {
+    let (target, operands) = line.split_once(": ")?;
+    // With only digits and spaces left, every extracted number is a whole operand
+    if !operands.bytes().all(|b| b.is_ascii_digit() || b == b' ') {
+        return None;
+    }
+    let operands = uints(operands).ok()?;
+    if operands.is_empty() {
+        return None;
+    }
+    Some(Equation {
+        target: parse_int(target)?,
+        operands,
+    })
+}
+
 /// Parses the input string into a vector of equations
 /// Each equation is represented as a tuple of (target_value, operands)
-fn parse_input(input: &str) -> Vec<(u64, Vec<u64>)> {
-    input
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() != 2 {
-                panic!("Invalid input format");
-            }
+pub fn parse(input: &str) -> Result<Vec<(u64, Vec<u64>)>, ParseError> {
+    Ok(parse_lines_by(input, ParseMode::Strict, parse_equation)?
+        .items
+        .into_iter()
+        .map(|equation| (equation.target, equation.operands))
+        .collect())
+}
 
-            let target_value = parts[0].trim().parse::<u64>().unwrap();
-            let operands = parts[1]
-                .split_whitespace()
-                .map(|s| s.parse::<u64>().unwrap())
-                .collect();
+/// A binary operator that can be placed between two operands of an equation.
+/// Operators are always evaluated left-to-right, ignoring precedence rules.
+pub trait Operator {
+    /// Applies the operator, returning None when the result is undefined or overflows
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64>;
+
+    /// Symbol used when printing a witness expression
+    fn symbol(&self) -> &str;
+
+    /// True if the result is never less than `lhs` when `rhs` is at least 1, which lets
+    /// the search give up on a branch once it overshoots the target
+    fn never_decreases(&self) -> bool {
+        false
+    }
+}
+
+/// Addition: `a + b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Add;
+
+/// Multiplication: `a * b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mul;
+
+/// Subtraction: `a - b`, undefined when the result would be negative
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sub;
+
+/// Integer (truncating) division: `a / b`, undefined when `b` is zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Div;
+
+/// Exponentiation: `a ^ b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pow;
+
+/// Concatenation of the digits of both operands in the given base: `a || b`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Concat {
+    pub base: u64,
+}
+
+impl Operator for Add {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_add(rhs)
+    }
+
+    fn symbol(&self) -> &str {
+        "+"
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
+
+impl Operator for Mul {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_mul(rhs)
+    }
+
+    fn symbol(&self) -> &str {
+        "*"
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
 
-            (target_value, operands)
+impl Operator for Sub {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_sub(rhs)
+    }
+
+    fn symbol(&self) -> &str {
+        "-"
+    }
+}
+
+impl Operator for Div {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_div(rhs)
+    }
+
+    fn symbol(&self) -> &str {
+        "/"
+    }
+}
+
+impl Operator for Pow {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_pow(u32::try_from(rhs).ok()?)
+    }
+
+    fn symbol(&self) -> &str {
+        "^"
+    }
+}
+
+impl Operator for Concat {
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        if self.base < 2 {
+            return None;
+        }
+
+        // Shift lhs left by the number of digits rhs has in the base
+        let mut shift = self.base;
+        while shift <= rhs {
+            shift = shift.checked_mul(self.base)?;
+        }
+
+        lhs.checked_mul(shift)?.checked_add(rhs)
+    }
+
+    fn symbol(&self) -> &str {
+        "||"
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
+
+/// Operators allowed in part 1
+pub const PART1_OPERATORS: &[&dyn Operator] = &[&Add, &Mul];
+
+/// Operators allowed in part 2
+pub const PART2_OPERATORS: &[&dyn Operator] = &[&Add, &Mul, &Concat { base: 10 }];
+
+/// A fully assigned equation, e.g. `81 + 40 * 27`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    pub operands: Vec<u64>,
+    pub symbols: Vec<String>,
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((first, rest)) = self.operands.split_first() else {
+            return Ok(());
+        };
+
+        write!(f, "{}", first)?;
+        for (symbol, operand) in self.symbols.iter().zip(rest) {
+            write!(f, " {} {}", symbol, operand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Result of solving a single equation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// First valid assignment found, in operator-set order
+    pub witness: Expression,
+    /// Number of operator assignments that produce the target
+    pub count: u64,
+}
+
+/// How many operands may remain for the search to drop a branch that has overshot the
+/// target: that needs every operator to never decrease, and no zero operand left that
+/// could bring the value back down. None if the operators rule pruning out.
+fn prune_depth(operands: &[u64], operators: &[&dyn Operator]) -> Option<usize> {
+    operators
+        .iter()
+        .all(|operator| operator.never_decreases())
+        .then(|| {
+            operands
+                .iter()
+                .rev()
+                .take_while(|&&operand| operand != 0)
+                .count()
         })
-        .collect()
 }
 
-pub fn part1(input: &str) -> u64 {
-    let equations = parse_input(input);
+/// Walks every operator assignment depth-first, calling `on_match` with the chosen
+/// operator indices whenever the target is hit. Stops early if `on_match` returns false.
+/// Branches that overshoot the target are skipped once at most `prune_depth` operands remain.
+fn search<F>(
+    target: u64,
+    acc: u64,
+    rest: &[u64],
+    operators: &[&dyn Operator],
+    prune_depth: Option<usize>,
+    chosen: &mut Vec<usize>,
+    on_match: &mut F,
+) -> bool
+where
+    F: FnMut(&[usize]) -> bool,
+{
+    if acc > target && prune_depth.is_some_and(|depth| rest.len() <= depth) {
+        return true;
+    }
+    let Some((&operand, rest)) = rest.split_first() else {
+        return acc != target || on_match(chosen);
+    };
 
-    equations
-        .into_iter()
-        .filter(|(target, operands)| {
-            if operands.is_empty() {
+    for (index, operator) in operators.iter().enumerate() {
+        if let Some(value) = operator.apply(acc, operand) {
+            chosen.push(index);
+            let keep_going = search(
+                target,
+                value,
+                rest,
+                operators,
+                prune_depth,
+                chosen,
+                on_match,
+            );
+            chosen.pop();
+
+            if !keep_going {
                 return false;
             }
+        }
+    }
 
-            // Start with the first operand
-            let mut results = vec![operands[0]];
+    true
+}
 
-            // Process remaining operands
-            for &operand in &operands[1..] {
-                let mut new_results = Vec::new();
+/// Checks whether any operator assignment makes the equation true
+pub fn is_solvable(target: u64, operands: &[u64], operators: &[&dyn Operator]) -> bool {
+    let Some((&first, rest)) = operands.split_first() else {
+        return false;
+    };
 
-                for &result in &results {
-                    // Addition
-                    new_results.push(result + operand);
-                    // Multiplication
-                    new_results.push(result * operand);
-                }
+    let prune_depth = prune_depth(operands, operators);
+    let mut found = false;
+    search(
+        target,
+        first,
+        rest,
+        operators,
+        prune_depth,
+        &mut Vec::new(),
+        &mut |_| {
+            found = true;
+            false
+        },
+    );
 
-                results = new_results;
-            }
+    found
+}
 
-            // Check if target is in results
-            results.contains(target)
-        })
+/// Enumerates all operator assignments, returning a witness expression and the
+/// number of valid assignments, or None if the equation cannot be solved
+pub fn solve(target: u64, operands: &[u64], operators: &[&dyn Operator]) -> Option<Solution> {
+    let (&first, rest) = operands.split_first()?;
+
+    let prune_depth = prune_depth(operands, operators);
+    let mut witness: Option<Vec<usize>> = None;
+    let mut count = 0;
+    search(
+        target,
+        first,
+        rest,
+        operators,
+        prune_depth,
+        &mut Vec::new(),
+        &mut |chosen| {
+            witness.get_or_insert_with(|| chosen.to_vec());
+            count += 1;
+            true
+        },
+    );
+
+    let symbols = witness?
+        .into_iter()
+        .map(|index| operators[index].symbol().to_string())
+        .collect();
+
+    Some(Solution {
+        witness: Expression {
+            operands: operands.to_vec(),
+            symbols,
+        },
+        count,
+    })
+}
+
+/// Sums the targets of all equations solvable with the given operators
+pub fn total_calibration(input: &str, operators: &[&dyn Operator]) -> Result<u64, ParseError> {
+    Ok(calibration(&parse(input)?, operators))
+}
+
+/// [`total_calibration`] over equations that are already parsed
+pub fn calibration(equations: &[(u64, Vec<u64>)], operators: &[&dyn Operator]) -> u64 {
+    equations
+        .iter()
+        .filter(|(target, operands)| is_solvable(*target, operands, operators))
         .map(|(target, _)| target)
         .sum()
 }
 
+pub fn part1(input: &str) -> u64 {
+    total_calibration(input, PART1_OPERATORS).unwrap_or_else(|err| panic!("{}", err))
+}
+
 pub fn part2(input: &str) -> u64 {
-    let equations = parse_input(input);
+    total_calibration(input, PART2_OPERATORS).unwrap_or_else(|err| panic!("{}", err))
+}
 
-    equations
-        .into_iter()
-        .filter(|(target, operands)| {
-            if operands.is_empty() {
-                return false;
-            }
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic with.
+/// The search is exponential in the operands per equation, so equations longer than a
+/// real input's 12 operands are out of scope and may take arbitrarily long.
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    let equations = parse(input)?;
+    Ok((
+        calibration(&equations, PART1_OPERATORS),
+        calibration(&equations, PART2_OPERATORS),
+    ))
+}
 
-            // Start with the first operand
-            let mut results = vec![operands[0]];
-
-            // Process remaining operands
-            for &operand in &operands[1..] {
-                let mut new_results = Vec::new();
-
-                for &result in &results {
-                    // Addition
-                    new_results.push(result + operand);
-                    // Multiplication
-                    new_results.push(result * operand);
-                    // Concatenation
-                    let concat_value = format!("{}{}", result, operand).parse::<u64>().unwrap();
-                    new_results.push(concat_value);
-                }
+/// Generate `equations` equations of 2 to 10 operands. A third are solvable with `+` and
+/// `*`, a third only once `||` is allowed, and the rest cannot be solved at all.
+pub fn generate(equations: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut input = String::new();
 
-                results = new_results;
-            }
+    for index in 0..equations {
+        write_equation(&mut input, random_equation(&mut rng, index % 3, 2..11));
+    }
 
-            // Check if target is in results
-            results.contains(target)
-        })
-        .map(|(target, _)| target)
-        .sum()
+    input
+}
+
+/// Generate 30 equations of exactly `operands` operands each, at least two, grouped as in
+/// [`generate`], to time the search as equations get longer
+pub fn generate_operands(operands: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut input = String::new();
+    let operands = operands.max(2) as u64;
+
+    for index in 0..30 {
+        write_equation(
+            &mut input,
+            random_equation(&mut rng, index % 3, operands..operands + 1),
+        );
+    }
+
+    input
+}
+
+/// An equation with an operand count drawn from `counts`, in `group` 0 (solvable with `+`
+/// and `*`), 1 (solvable only with `||`) or 2 (unsolvable)
+fn random_equation(rng: &mut Rng, group: usize, counts: Range<u64>) -> (u64, Vec<u64>) {
+    loop {
+        let operands: Vec<u64> = (0..rng.range(counts.clone()))
+            .map(|_| match rng.chance(0.7) {
+                true => rng.range(1..100),
+                false => rng.range(100..1000),
+            })
+            .collect();
+        let operators = if group == 0 {
+            PART1_OPERATORS
+        } else {
+            PART2_OPERATORS
+        };
+        let value = operands[1..].iter().try_fold(operands[0], |acc, &operand| {
+            rng.choose(operators).apply(acc, operand)
+        });
+        let Some(value) = value else {
+            continue;
+        };
+
+        // Keep the equation only if it lands in the intended group
+        let target = match group {
+            0 => value,
+            1 if !is_solvable(value, &operands, PART1_OPERATORS) => value,
+            2 => value + rng.range(1..1000),
+            _ => continue,
+        };
+        if group != 2 || !is_solvable(target, &operands, PART2_OPERATORS) {
+            return (target, operands);
+        }
+    }
+}
+
+/// Append an equation as a line of input
+fn write_equation(input: &mut String, (target, operands): (u64, Vec<u64>)) {
+    let operands: Vec<String> = operands.iter().map(u64::to_string).collect();
+    writeln!(input, "{}: {}", target, operands.join(" ")).unwrap();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "190: 10 19
 3267: 81 40 27
@@ -115,4 +456,159 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 11387);
     }
+
+    #[test]
+    fn test_solve_witness_and_count() {
+        let solution = solve(3267, &[81, 40, 27], PART1_OPERATORS).unwrap();
+        assert_eq!(solution.witness.to_string(), "81 + 40 * 27");
+        assert_eq!(solution.count, 2);
+
+        let solution = solve(7290, &[6, 8, 6, 15], PART2_OPERATORS).unwrap();
+        assert_eq!(solution.witness.to_string(), "6 * 8 || 6 * 15");
+        assert_eq!(solution.count, 1);
+
+        assert_eq!(solve(83, &[17, 5], PART1_OPERATORS), None);
+    }
+
+    #[test]
+    fn test_search_prunes_overshoot() {
+        // 3^39 assignments without pruning; every branch overshoots at the first operator
+        assert!(!is_solvable(1, &[2; 40], PART2_OPERATORS));
+
+        // A later zero can still bring an overshooting branch back to the target
+        let solution = solve(0, &[5, 3, 0], PART1_OPERATORS).unwrap();
+        assert_eq!(solution.count, 2);
+        assert_eq!(solution.witness.to_string(), "5 + 3 * 0");
+        assert_eq!(prune_depth(&[5, 0, 3, 4], PART2_OPERATORS), Some(2));
+        assert_eq!(prune_depth(&[5, 3], &[&Add, &Sub]), None);
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let operators: &[&dyn Operator] = &[&Sub, &Div, &Pow];
+        let solution = solve(2, &[10, 2, 3], operators).unwrap();
+        assert_eq!(solution.witness.to_string(), "10 - 2 / 3");
+        assert_eq!(solution.count, 2); // also 10 / 2 - 3
+        assert!(is_solvable(64, &[2, 3, 2], operators));
+        assert!(!is_solvable(1, &[2, 0], &[&Div]));
+
+        // 0b101 || 0b11 == 0b10111
+        assert_eq!(Concat { base: 2 }.apply(5, 3), Some(23));
+        assert_eq!(Concat { base: 10 }.apply(12, 0), Some(120));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("190: 10 19\n3267 81 40 27").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 1, 11..24));
+        assert_eq!(err.message, "expected ':' after the test value");
+
+        let err = parse("190: 10 x9").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 9));
+        assert_eq!(err.message, "invalid operand 'x9'");
+
+        let err = parse("7290:  ").unwrap_err();
+        assert_eq!(
+            (err.column, err.message.as_str()),
+            (6, "expected at least 1 operand")
+        );
+    }
+
+    /// Naive reference: the number of operator assignments, out of all
+    /// `operators^(n - 1)`, that evaluate left to right to the target without overflow
+    fn naive_count(target: u64, operands: &[u64], operators: usize) -> u64 {
+        let slots = operands.len() - 1;
+        (0..operators.pow(slots as u32))
+            .filter(|&assignment| {
+                let mut code = assignment;
+                let mut value = Some(operands[0]);
+                for &operand in &operands[1..] {
+                    value = value.and_then(|acc| match code % operators {
+                        0 => acc.checked_add(operand),
+                        1 => acc.checked_mul(operand),
+                        _ => format!("{}{}", acc, operand).parse().ok(),
+                    });
+                    code /= operators;
+                }
+                value == Some(target)
+            })
+            .count() as u64
+    }
+
+    /// Equations whose target is either random or produced by random operators, so
+    /// that both solvable and unsolvable cases are common
+    fn equation_strategy() -> impl Strategy<Value = (u64, Vec<u64>)> {
+        (
+            prop::collection::vec(1u64..30, 1..7),
+            prop::collection::vec(0u8..3, 6),
+            0u64..5000,
+            any::<bool>(),
+        )
+            .prop_map(|(operands, choices, random, use_random)| {
+                let built = operands[1..].iter().zip(&choices).try_fold(
+                    operands[0],
+                    |acc, (&operand, choice)| match choice {
+                        0 => acc.checked_add(operand),
+                        1 => acc.checked_mul(operand),
+                        _ => format!("{}{}", acc, operand).parse().ok(),
+                    },
+                );
+                let target = if use_random {
+                    random
+                } else {
+                    built.unwrap_or(random)
+                };
+                (target, operands)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn parts_match_enumeration(equations in prop::collection::vec(equation_strategy(), 0..10)) {
+            let input: String = equations
+                .iter()
+                .map(|(target, operands)| {
+                    let operands: Vec<String> = operands.iter().map(u64::to_string).collect();
+                    format!("{}: {}\n", target, operands.join(" "))
+                })
+                .collect();
+            let naive_total = |operators| {
+                equations
+                    .iter()
+                    .filter(|(target, operands)| naive_count(*target, operands, operators) > 0)
+                    .map(|(target, _)| target)
+                    .sum::<u64>()
+            };
+
+            prop_assert_eq!(part1(&input), naive_total(2));
+            prop_assert_eq!(part2(&input), naive_total(3));
+        }
+
+        #[test]
+        fn quick_equation_agrees_with_derived(line in "[-+0-9: x]{0,14}") {
+            if let Some(equation) = quick_equation(&line) {
+                prop_assert_eq!(line.parse::<Equation>(), Ok(equation));
+            }
+        }
+
+        #[test]
+        fn solution_counts_match_enumeration((target, operands) in equation_strategy()) {
+            let count = solve(target, &operands, PART2_OPERATORS).map_or(0, |s| s.count);
+            prop_assert_eq!(count, naive_count(target, &operands, 3));
+        }
+
+        #[test]
+        fn generated_equations_fall_in_their_groups(count in 0usize..9, seed in any::<u64>()) {
+            let equations = parse(&generate(count, seed)).unwrap();
+            prop_assert_eq!(equations.len(), count);
+            for (index, (target, operands)) in equations.iter().enumerate() {
+                let (add_mul, all) = (naive_count(*target, operands, 2), naive_count(*target, operands, 3));
+                match index % 3 {
+                    0 => prop_assert!(add_mul > 0),
+                    1 => prop_assert!(add_mul == 0 && all > 0),
+                    _ => prop_assert!(all == 0),
+                }
+            }
+        }
+    }
 }
@@ -1,126 +1,212 @@
-use std::collections::HashSet;
-
-/// Convert input string to 2D character grid
-fn parse_grid(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.chars().collect())
-        .collect()
-}
+use aoc_common::ParseError;
+use aoc_common::rng::Rng;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-/// Create all possible ordered pairs between elements of two lists
-fn create_ordered_pairs<T: Clone>(list1: &[T], list2: &[T]) -> Vec<(T, T)> {
-    list1
-        .iter()
-        .flat_map(|item1| {
-            list2
-                .iter()
-                .map(move |item2| (item1.clone(), item2.clone()))
-        })
-        .collect()
-}
+/// Glyph marking an empty cell
+pub const EMPTY: char = '.';
 
-/// Calculate positions along the line from start to end within grid bounds
-fn calculate_antinode_positions(
-    grid_size: usize,
-    (start_row, start_col): (usize, usize),
-    (end_row, end_col): (usize, usize),
-) -> Vec<(usize, usize)> {
-    if (start_row, start_col) == (end_row, end_col) {
-        return Vec::new();
-    }
-
-    let row_step = end_row as isize - start_row as isize;
-    let col_step = end_col as isize - start_col as isize;
+/// Frequencies used by the puzzle: digits, lowercase and uppercase letters
+pub fn default_frequencies() -> Vec<char> {
+    ('0'..='9').chain('a'..='z').chain('A'..='Z').collect()
+}
 
-    let mut result = Vec::new();
-    let mut current = (end_row as isize, end_col as isize);
+/// A rectangular map with antenna positions grouped by frequency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AntennaMap {
+    pub rows: usize,
+    pub cols: usize,
+    pub antennas: BTreeMap<char, Vec<(usize, usize)>>,
+}
 
-    loop {
-        current = (current.0 + row_step, current.1 + col_step);
+impl AntennaMap {
+    /// Parse a map, accepting only `EMPTY` and the given frequency glyphs
+    pub fn parse(input: &str, frequencies: &[char]) -> Result<Self, ParseError> {
+        let grid = aoc_common::grid::parse_rows(input)?;
+        let rows = grid.len();
+        let cols = grid.first().map_or(0, Vec::len);
+        let mut antennas: BTreeMap<char, Vec<(usize, usize)>> = BTreeMap::new();
 
-        if current.0 >= 0
-            && current.0 < grid_size as isize
-            && current.1 >= 0
-            && current.1 < grid_size as isize
-        {
-            result.push((current.0 as usize, current.1 as usize));
-        } else {
-            break;
+        let lines = input.lines().filter(|line| !line.trim().is_empty());
+        for (row, line) in lines.enumerate() {
+            for (col, (offset, cell)) in line.char_indices().enumerate() {
+                if cell == EMPTY {
+                    continue;
+                }
+                if !frequencies.contains(&cell) {
+                    let glyph = &line[offset..offset + cell.len_utf8()];
+                    return Err(ParseError::at(
+                        input,
+                        glyph,
+                        format!("unknown glyph '{}'", cell),
+                    ));
+                }
+                antennas.entry(cell).or_default().push((row, col));
+            }
         }
+
+        Ok(Self {
+            rows,
+            cols,
+            antennas,
+        })
     }
+}
 
-    result
+/// Which positions along the line through two antennas count as antinodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntinodeModel {
+    /// One position beyond each antenna, at the same distance as between them (part 1)
+    Nearest,
+    /// Every position on the line reached by whole multiples of the antenna distance,
+    /// including the antennas themselves (part 2 as scored by the puzzle)
+    Harmonics,
+    /// Every lattice point on the line, stepping by the distance reduced by its gcd
+    Exact,
 }
 
-/// Core solver function for both problem parts
-fn solve_grid<F>(grid: &[Vec<char>], position_mapper: F) -> usize
-where
-    F: Fn(usize, (usize, usize), (usize, usize)) -> Vec<(usize, usize)>,
-{
-    let grid_size = grid.len();
-    assert!(grid.iter().all(|row| row.len() == grid_size));
+/// Antinode positions grouped by the frequency that created them
+pub type AntinodesByFrequency = BTreeMap<char, BTreeSet<(usize, usize)>>;
 
-    // Create a list of valid characters (digits, lowercase and uppercase letters)
-    let valid_chars: Vec<char> = ('0'..='9').chain('a'..='z').chain('A'..='Z').collect();
+/// Every unordered pair of distinct items
+fn unordered_pairs<T>(items: &[T]) -> impl Iterator<Item = (&T, &T)> {
+    items
+        .iter()
+        .enumerate()
+        .flat_map(move |(i, first)| items[i + 1..].iter().map(move |second| (first, second)))
+}
 
-    // Find positions of each character in the grid
-    let mut result_positions = HashSet::new();
+/// Greatest common divisor of two non-negative numbers
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
 
-    for &char in &valid_chars {
-        let mut char_locations = Vec::new();
+/// Positions `origin + k * step` for k = 1, 2, ... while they stay inside the map
+fn ray(
+    (rows, cols): (usize, usize),
+    (row, col): (usize, usize),
+    (row_step, col_step): (isize, isize),
+) -> impl Iterator<Item = (usize, usize)> {
+    std::iter::successors(Some((row as isize, col as isize)), move |&(r, c)| {
+        Some((r + row_step, c + col_step))
+    })
+    .skip(1)
+    .map_while(move |(r, c)| {
+        (r >= 0 && r < rows as isize && c >= 0 && c < cols as isize)
+            .then_some((r as usize, c as usize))
+    })
+}
 
-        // Find all positions of this character
-        for (row, grid_row) in grid.iter().enumerate() {
-            for (col, &cell) in grid_row.iter().enumerate() {
-                if cell == char {
-                    char_locations.push((row, col));
-                }
-            }
-        }
+/// Antinodes produced by one pair of antennas under the given model
+fn pair_antinodes(
+    bounds: (usize, usize),
+    a: (usize, usize),
+    b: (usize, usize),
+    model: AntinodeModel,
+) -> Vec<(usize, usize)> {
+    let delta = (b.0 as isize - a.0 as isize, b.1 as isize - a.1 as isize);
+    let reverse = (-delta.0, -delta.1);
 
-        // Generate all pair combinations
-        let pairs = create_ordered_pairs(&char_locations, &char_locations);
+    match model {
+        AntinodeModel::Nearest => ray(bounds, b, delta)
+            .take(1)
+            .chain(ray(bounds, a, reverse).take(1))
+            .collect(),
+        AntinodeModel::Harmonics | AntinodeModel::Exact => {
+            let divisor = match model {
+                AntinodeModel::Exact => gcd(delta.0.unsigned_abs(), delta.1.unsigned_abs()),
+                _ => 1,
+            } as isize;
+            let step = (delta.0 / divisor, delta.1 / divisor);
 
-        // Map positions using the provided mapper function
-        for ((r1, c1), (r2, c2)) in pairs {
-            let mapped_points = position_mapper(grid_size, (r1, c1), (r2, c2));
-            result_positions.extend(mapped_points);
+            std::iter::once(a)
+                .chain(ray(bounds, a, step))
+                .chain(ray(bounds, a, (-step.0, -step.1)))
+                .collect()
         }
     }
+}
+
+/// Antinode coordinates for every frequency on the map
+pub fn antinodes(map: &AntennaMap, model: AntinodeModel) -> AntinodesByFrequency {
+    let bounds = (map.rows, map.cols);
+
+    map.antennas
+        .iter()
+        .map(|(&frequency, locations)| {
+            let positions = unordered_pairs(locations)
+                .flat_map(|(&a, &b)| pair_antinodes(bounds, a, b, model))
+                .collect();
+            (frequency, positions)
+        })
+        .collect()
+}
 
-    result_positions.len()
+/// Number of distinct positions that are an antinode of any frequency
+pub fn count_antinodes(map: &AntennaMap, model: AntinodeModel) -> usize {
+    antinodes(map, model)
+        .values()
+        .flatten()
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Parse a map with the default frequencies, panicking on malformed input
+fn parse_map(input: &str) -> AntennaMap {
+    AntennaMap::parse(input, &default_frequencies()).unwrap_or_else(|err| panic!("{}", err))
 }
 
 /// Part 1: Consider the first valid antinode position
 pub fn part1(input: &str) -> u64 {
-    let grid = parse_grid(input);
-
-    solve_grid(&grid, |size, (r1, c1), (r2, c2)| {
-        let positions = calculate_antinode_positions(size, (r1, c1), (r2, c2));
-        if positions.is_empty() {
-            Vec::new()
-        } else {
-            vec![positions[0]]
-        }
-    }) as u64
+    count_antinodes(&parse_map(input), AntinodeModel::Nearest) as u64
 }
 
 /// Part 2: Include endpoint and all valid antinodes
 pub fn part2(input: &str) -> u64 {
-    let grid = parse_grid(input);
+    count_antinodes(&parse_map(input), AntinodeModel::Harmonics) as u64
+}
 
-    solve_grid(&grid, |size, (r1, c1), (r2, c2)| {
-        let mut result = vec![(r2, c2)];
-        result.extend(calculate_antinode_positions(size, (r1, c1), (r2, c2)));
-        result
-    }) as u64
+/// Both answers from a single parse, or the error that `part1` and `part2` would panic with
+pub fn answers(input: &str) -> Result<(u64, u64), ParseError> {
+    let map = AntennaMap::parse(input, &default_frequencies())?;
+    Ok((
+        count_antinodes(&map, AntinodeModel::Nearest) as u64,
+        count_antinodes(&map, AntinodeModel::Harmonics) as u64,
+    ))
+}
+
+/// Generate a `size` by `size` map with three or four antennas for each of a number of
+/// frequencies growing with the area, up to all 62
+pub fn generate(size: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut frequencies = default_frequencies();
+    rng.shuffle(&mut frequencies);
+    frequencies.truncate((size * size / 60).clamp(1, 62));
+
+    let mut cells: Vec<(usize, usize)> = (0..size)
+        .flat_map(|row| (0..size).map(move |col| (row, col)))
+        .collect();
+    rng.shuffle(&mut cells);
+
+    let mut map = vec![vec![EMPTY; size]; size];
+    for frequency in frequencies {
+        for _ in 0..rng.range(3..5) {
+            let Some((row, col)) = cells.pop() else {
+                break;
+            };
+            map[row][col] = frequency;
+        }
+    }
+
+    map.into_iter()
+        .map(|row| row.into_iter().collect::<String>() + "\n")
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_common::metamorphic;
+    use proptest::prelude::*;
 
     const EXAMPLE_INPUT: &str = "............
 ........0...
@@ -144,4 +230,165 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_INPUT), 34);
     }
+
+    const WIDE_INPUT: &str = "..........
+..........
+..........
+....a.....
+........a.
+.....a....
+..........
+..........";
+
+    #[test]
+    fn test_non_square_maps() {
+        // The example from the puzzle text cropped to 8 rows
+        assert_eq!(part1(WIDE_INPUT), 4);
+
+        let tall = "..
+.a
+.a
+..
+..
+..";
+        assert_eq!(part1(tall), 2);
+        assert_eq!(part2(tall), 6);
+
+        let map = AntennaMap::parse(WIDE_INPUT, &default_frequencies()).unwrap();
+        assert_eq!((map.rows, map.cols), (8, 10));
+    }
+
+    #[test]
+    fn test_custom_frequencies() {
+        let input = "*....
+.....
+..*..
+.....
+....+";
+        let err = AntennaMap::parse(input, &default_frequencies()).unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (1, 1, 0..1));
+        assert_eq!(err.message, "unknown glyph '*'");
+
+        let map = AntennaMap::parse(input, &['*', '+']).unwrap();
+        assert_eq!(count_antinodes(&map, AntinodeModel::Nearest), 1);
+        assert_eq!(count_antinodes(&map, AntinodeModel::Harmonics), 3);
+
+        let err = AntennaMap::parse("...\n..", &['a']).unwrap_err();
+        assert_eq!((err.line, err.column, err.span), (2, 1, 4..6));
+    }
+
+    #[test]
+    fn test_exact_antinodes() {
+        // The antennas are 4 columns and 2 rows apart, so (1, 2) lies between them
+        let input = "a....
+.....
+....a";
+        let map = AntennaMap::parse(input, &default_frequencies()).unwrap();
+        assert_eq!(count_antinodes(&map, AntinodeModel::Harmonics), 2);
+
+        let exact = antinodes(&map, AntinodeModel::Exact);
+        let positions: Vec<(usize, usize)> = exact[&'a'].iter().copied().collect();
+        assert_eq!(positions, vec![(0, 0), (1, 2), (2, 4)]);
+
+        // Puzzle inputs never place antennas with a common factor, so both models agree
+        let map = AntennaMap::parse(EXAMPLE_INPUT, &default_frequencies()).unwrap();
+        assert_eq!(count_antinodes(&map, AntinodeModel::Exact), 34);
+    }
+
+    #[test]
+    fn test_antinodes_by_frequency() {
+        let map = AntennaMap::parse(EXAMPLE_INPUT, &default_frequencies()).unwrap();
+        let by_frequency = antinodes(&map, AntinodeModel::Nearest);
+        assert_eq!(by_frequency.keys().collect::<Vec<_>>(), vec![&'0', &'A']);
+        assert_eq!(by_frequency[&'0'].len(), 10);
+        assert!(by_frequency[&'A'].contains(&(7, 7)));
+        assert_eq!(
+            unordered_pairs(&[1, 2, 3]).collect::<Vec<_>>(),
+            vec![(&1, &2), (&1, &3), (&2, &3)]
+        );
+    }
+
+    /// Naive reference: test every cell against every ordered pair of same-frequency antennas
+    fn naive(map: &AntennaMap, model: AntinodeModel) -> usize {
+        let is_antinode =
+            |(r, c): (isize, isize), (ar, ac): (isize, isize), (br, bc): (isize, isize)| {
+                let (dr, dc) = (br - ar, bc - ac);
+                let (pr, pc) = (r - ar, c - ac);
+                let collinear = pr * dc == pc * dr;
+                match model {
+                    AntinodeModel::Nearest => (pr, pc) == (2 * dr, 2 * dc),
+                    AntinodeModel::Harmonics => {
+                        collinear && if dr != 0 { pr % dr == 0 } else { pc % dc == 0 }
+                    }
+                    AntinodeModel::Exact => collinear,
+                }
+            };
+
+        let mut count = 0;
+        for r in 0..map.rows as isize {
+            for c in 0..map.cols as isize {
+                let found = map.antennas.values().any(|locations| {
+                    locations.iter().any(|&(ar, ac)| {
+                        locations.iter().any(|&(br, bc)| {
+                            (ar, ac) != (br, bc)
+                                && is_antinode(
+                                    (r, c),
+                                    (ar as isize, ac as isize),
+                                    (br as isize, bc as isize),
+                                )
+                        })
+                    })
+                });
+                count += usize::from(found);
+            }
+        }
+        count
+    }
+
+    /// Small maps with a few antennas of a few frequencies
+    fn map_strategy() -> impl Strategy<Value = String> {
+        (1usize..12, 1usize..12).prop_flat_map(|(rows, cols)| {
+            let cell = prop::sample::select(vec!['.', '.', '.', '.', '.', '.', 'a', 'b', '0']);
+            prop::collection::vec(prop::collection::vec(cell, cols), rows).prop_map(|rows| {
+                rows.iter()
+                    .map(|row| row.iter().collect::<String>() + "\n")
+                    .collect()
+            })
+        })
+    }
+
+    #[test]
+    fn test_metamorphic_example() {
+        let transforms = metamorphic::grid_symmetries();
+        metamorphic::check_both(part1, part2, EXAMPLE_INPUT, &transforms).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn counts_match_naive(input in map_strategy()) {
+            let map = AntennaMap::parse(&input, &default_frequencies()).unwrap();
+            for model in [AntinodeModel::Nearest, AntinodeModel::Harmonics, AntinodeModel::Exact] {
+                prop_assert_eq!(count_antinodes(&map, model), naive(&map, model));
+            }
+            prop_assert_eq!(part1(&input), naive(&map, AntinodeModel::Nearest) as u64);
+            prop_assert_eq!(part2(&input), naive(&map, AntinodeModel::Harmonics) as u64);
+        }
+
+        #[test]
+        fn generated_maps_match_naive(size in 0usize..24, seed in any::<u64>()) {
+            let input = generate(size, seed);
+            let map = AntennaMap::parse(&input, &default_frequencies()).unwrap();
+            prop_assert_eq!((map.rows, map.cols), (size, size));
+            prop_assert_eq!(part1(&input), naive(&map, AntinodeModel::Nearest) as u64);
+            prop_assert_eq!(part2(&input), naive(&map, AntinodeModel::Harmonics) as u64);
+        }
+
+        #[test]
+        fn generated_inputs_are_invariant(size in 0usize..20, seed in any::<u64>()) {
+            let transforms = metamorphic::grid_symmetries();
+            let input = generate(size, seed);
+            let checked = metamorphic::check_both(part1, part2, &input, &transforms);
+            prop_assert_eq!(checked, Ok(()));
+        }
+    }
 }
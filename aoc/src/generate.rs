@@ -0,0 +1,100 @@
+//! Random valid puzzle inputs for stress tests and benchmarks
+
+use std::error::Error;
+
+/// Builds an input of a given size from a seed
+pub type Generator = fn(usize, u64) -> String;
+
+/// A day's input generator and the size of a real puzzle input, in the generator's
+/// own unit: lines, reports, bytes, grid side or updates
+pub fn generator(year: u16, day: u8) -> Result<(Generator, usize), Box<dyn Error>> {
+    let found: (Generator, usize) = match (year, day) {
+        (2024, 1) => (day01::generate, 1000),
+        (2024, 2) => (day02::generate, 1000),
+        (2024, 3) => (day03::generate, 18_000),
+        (2024, 4) => (day04::generate, 140),
+        (2024, 5) => (day05::generate, 200),
+        (2024, 6) => (day06::generate, 130),
+        (2024, 7) => (day07::generate, 850),
+        (2024, 8) => (day08::generate, 50),
+        _ => return Err(format!("no input generator for {} day {}", year, day).into()),
+    };
+    Ok(found)
+}
+
+/// A generator that grows a quantity other than a day's default size, named by `axis`,
+/// and that quantity in a real puzzle input
+pub fn axis_generator(
+    year: u16,
+    day: u8,
+    axis: &str,
+) -> Result<(Generator, usize), Box<dyn Error>> {
+    let found: (Generator, usize) = match (year, day, axis) {
+        (2024, 7, "operands") => (day07::generate_operands, 12),
+        _ => return Err(format!("no {} axis for {} day {}", axis, year, day).into()),
+    };
+    Ok(found)
+}
+
+/// Generate an input for a puzzle, as large as a real one unless `size` is given
+pub fn generate(
+    year: u16,
+    day: u8,
+    size: Option<usize>,
+    seed: u64,
+) -> Result<String, Box<dyn Error>> {
+    let (generate, real_size) = generator(year, day)?;
+    Ok(generate(size.unwrap_or(real_size), seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_common::ParseError;
+
+    /// Parse and solve an input with the day's fallible entry point
+    fn answers(day: u8, input: &str) -> Result<(), ParseError> {
+        match day {
+            1 => day01::answers(input).map(drop),
+            2 => day02::answers(input).map(drop),
+            3 => day03::answers(input).map(drop),
+            4 => day04::answers(input).map(drop),
+            5 => day05::answers(input).map(drop),
+            6 => day06::answers(input).map(drop),
+            7 => day07::answers(input).map(drop),
+            8 => day08::answers(input).map(drop),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_generated_inputs_are_valid() {
+        for day in 1..=8 {
+            let input = generate(2024, day, Some(20), 42).unwrap();
+            assert_eq!(input, generate(2024, day, Some(20), 42).unwrap());
+            assert_ne!(input, generate(2024, day, Some(20), 43).unwrap());
+
+            // Full size, except for day 6 whose brute force is slow in debug builds
+            let size = (day == 6).then_some(30);
+            let input = generate(2024, day, size, 1).unwrap();
+            answers(day, &input).unwrap_or_else(|err| panic!("day {}: {}", day, err));
+        }
+
+        let input = generate(2024, 1, None, 1).unwrap();
+        assert_eq!(input.lines().count(), 1000);
+        assert!(day01::part1(&input) > 0);
+        assert!(day05::part1(&generate(2024, 5, None, 1).unwrap()) > 0);
+        assert!(day06::part1(&generate(2024, 6, Some(30), 1).unwrap()) > 0);
+        assert!(generate(2024, 9, None, 1).is_err());
+
+        let (generate_operands, _) = axis_generator(2024, 7, "operands").unwrap();
+        let input = generate_operands(6, 1);
+        assert!(
+            input
+                .lines()
+                .all(|line| line.split_whitespace().count() == 7)
+        );
+        day07::answers(&input).unwrap();
+        assert!(axis_generator(2024, 6, "operands").is_err());
+    }
+}
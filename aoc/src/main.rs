@@ -0,0 +1,247 @@
+mod generate;
+mod play;
+mod render;
+mod scale;
+mod viz;
+
+use aoc_common::ParseError;
+use aoc_common::parse::EXIT_PARSE_ERROR;
+use clap::{Parser, Subcommand};
+use play::PlayOptions;
+use render::Renderer;
+use scale::ScaleOptions;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use viz::VizOptions;
+
+#[derive(Parser)]
+#[command(name = "aoc", about = "Tools for exploring Advent of Code solutions")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a grid puzzle as a PNG still or an animated GIF
+    Viz {
+        year: u16,
+        day: u8,
+        /// Output file; `.gif` writes every frame, any other extension writes the last frame as PNG
+        #[arg(long)]
+        out: PathBuf,
+        /// Puzzle input, defaulting to inputs/<year>/day<DD>.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Size of each grid cell in pixels
+        #[arg(long, default_value_t = 4)]
+        cell_size: usize,
+        /// Maximum number of frames for animated days, at least one
+        #[arg(long, default_value_t = NonZeroUsize::new(60).unwrap())]
+        frames: NonZeroUsize,
+        /// Delay between GIF frames in hundredths of a second
+        #[arg(long, default_value_t = 5)]
+        delay: u16,
+    },
+    /// Animate a simulation in the terminal
+    Play {
+        year: u16,
+        day: u8,
+        /// Puzzle input, defaulting to inputs/<year>/day<DD>.txt
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Simulation steps per second
+        #[arg(long, default_value_t = 20.0, value_parser = play::parse_speed)]
+        speed: f64,
+        /// Start paused; press 'n' to single-step or space to run
+        #[arg(long)]
+        paused: bool,
+    },
+    /// Print a random valid puzzle input, the same for the same seed
+    Gen {
+        year: u16,
+        day: u8,
+        /// Input size in the day's own unit, such as lines or grid side; defaults to the size
+        /// of a real input
+        #[arg(long)]
+        size: Option<usize>,
+        /// Seed for the random input; the same seed always gives the same input
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Time a solver on generated inputs of growing size and estimate its complexity
+    Scale {
+        year: u16,
+        day: u8,
+        /// Grow another quantity than the day's default size, such as `operands` for day 7
+        #[arg(long)]
+        axis: Option<String>,
+        /// Largest input size, defaulting to the size of a real input
+        #[arg(long)]
+        max_size: Option<usize>,
+        /// Number of sizes to time, each double the previous, or evenly spaced for an
+        /// exponential bound
+        #[arg(long, default_value_t = 5)]
+        steps: usize,
+        /// Milliseconds spent timing each size
+        #[arg(long, default_value_t = 200)]
+        budget: u64,
+        /// Seed for the generated inputs, so that runs can be repeated
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
+
+/// Puzzle input together with where it was read from
+struct Input {
+    path: PathBuf,
+    text: String,
+}
+
+impl Input {
+    /// Attach the input to parse errors so they can be reported with a snippet
+    fn annotate(&self, err: Box<dyn Error>) -> Box<dyn Error> {
+        match err.downcast::<ParseError>() {
+            Ok(error) => Box::new(InputError {
+                source: self.path.display().to_string(),
+                input: self.text.clone(),
+                error: *error,
+            }),
+            Err(err) => err,
+        }
+    }
+}
+
+/// A parse error with the input it points into
+#[derive(Debug)]
+struct InputError {
+    source: String,
+    input: String,
+    error: ParseError,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error.render(&self.input, &self.source))
+    }
+}
+
+impl Error for InputError {}
+
+/// Read the puzzle input from `path`, or from the repository's inputs directory
+fn read_input(year: u16, day: u8, path: Option<PathBuf>) -> io::Result<Input> {
+    let path = path.unwrap_or_else(|| PathBuf::from(format!("inputs/{}/day{:02}.txt", year, day)));
+    fs::read_to_string(&path)
+        .map(|input| Input {
+            text: input.trim().to_string(),
+            path: path.clone(),
+        })
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+fn is_gif(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Viz {
+            year,
+            day,
+            out,
+            input,
+            cell_size,
+            frames,
+            delay,
+        } => {
+            let input = read_input(year, day, input)?;
+            let options = VizOptions {
+                frames,
+                renderer: Renderer {
+                    cell_size,
+                    ..Renderer::default()
+                },
+            };
+            let images = viz::visualise(year, day, &input.text, &options)
+                .map_err(|err| input.annotate(err))?;
+            let writer = BufWriter::new(File::create(&out)?);
+
+            if is_gif(&out) {
+                render::write_gif(&images, delay, writer)?;
+            } else if let Some(last) = images.last() {
+                last.write_png(writer)?;
+            }
+
+            println!("Wrote {} frame(s) to {}", images.len(), out.display());
+        }
+        Command::Play {
+            year,
+            day,
+            input,
+            speed,
+            paused,
+        } => {
+            let input = read_input(year, day, input)?;
+            let mut sim =
+                play::simulation(year, day, &input.text).map_err(|err| input.annotate(err))?;
+            play::play(sim.as_mut(), PlayOptions { speed, paused })?;
+        }
+        Command::Gen {
+            year,
+            day,
+            size,
+            seed,
+        } => print!("{}", generate::generate(year, day, size, seed)?),
+        Command::Scale {
+            year,
+            day,
+            axis,
+            max_size,
+            steps,
+            budget,
+            seed,
+        } => {
+            let options = ScaleOptions {
+                axis,
+                max_size,
+                steps,
+                budget: Duration::from_millis(budget),
+                seed,
+            };
+            let report = scale::scale(year, day, &options)?;
+            println!("{}", report);
+            if report.is_worse_than_expected() {
+                eprintln!(
+                    "warning: {} day {} scales as {}, worse than the expected O({})",
+                    year,
+                    day,
+                    report.estimate(),
+                    report.expected.label
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<InputError>() => {
+            eprintln!("{}", err);
+            ExitCode::from(EXIT_PARSE_ERROR)
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
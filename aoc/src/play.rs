@@ -0,0 +1,369 @@
+//! Terminal playback of grid simulations using ANSI cursor control and colours
+
+use aoc_common::{Grid, Simulation};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Redraws per second at most; faster speeds advance several steps per redraw
+const MAX_REDRAWS: f64 = 60.0;
+
+/// Slowest and fastest playback in steps per second
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 1e6;
+
+/// Help text shown in the status line
+const KEYS: &str = "space pause  n step  +/- speed  arrows pan  f follow  q quit";
+
+/// Build the simulation for a given puzzle
+pub fn simulation(year: u16, day: u8, input: &str) -> Result<Box<dyn Simulation>, Box<dyn Error>> {
+    match (year, day) {
+        (2024, 6) => Ok(Box::new(day06::Walk::new(input)?)),
+        _ => Err(format!("no simulation for {} day {}", year, day).into()),
+    }
+}
+
+/// Settings for a playback session
+#[derive(Debug, Clone, Copy)]
+pub struct PlayOptions {
+    /// Simulation steps per second
+    pub speed: f64,
+    /// Start paused, waiting for a single-step or resume
+    pub paused: bool,
+}
+
+/// Parse a `--speed` argument, accepting only a finite number of steps per second above zero
+pub fn parse_speed(text: &str) -> Result<f64, String> {
+    let speed: f64 = text.parse().map_err(|err| format!("{}", err))?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(format!(
+            "expected a finite speed above zero, found {}",
+            text
+        ))
+    }
+}
+
+/// ANSI 256-colour foreground for a glyph, or None to use the terminal default
+pub fn glyph_color(glyph: char) -> Option<u8> {
+    match glyph {
+        '#' => Some(244),
+        '.' => Some(238),
+        'X' | 'O' => Some(37),
+        '^' | '>' | 'v' | '<' | '@' => Some(226),
+        c if c.is_ascii_alphanumeric() => Some(213),
+        _ => None,
+    }
+}
+
+/// The visible window onto a grid, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub row: usize,
+    pub col: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Viewport {
+    pub fn new(height: usize, width: usize) -> Self {
+        Viewport {
+            row: 0,
+            col: 0,
+            height,
+            width,
+        }
+    }
+
+    /// Move by a number of cells, staying within a grid of the given size
+    pub fn pan(&mut self, rows: isize, cols: isize, grid_height: usize, grid_width: usize) {
+        self.row = self.row.saturating_add_signed(rows);
+        self.col = self.col.saturating_add_signed(cols);
+        self.clamp(grid_height, grid_width);
+    }
+
+    /// Scroll just enough to keep `cell` a quarter of the window away from the edges
+    pub fn follow(&mut self, (row, col): (usize, usize), grid_height: usize, grid_width: usize) {
+        fn axis(start: usize, size: usize, target: usize) -> usize {
+            let margin = size / 4;
+            if target < start + margin {
+                target.saturating_sub(margin)
+            } else if target + margin >= start + size {
+                (target + margin + 1).saturating_sub(size)
+            } else {
+                start
+            }
+        }
+
+        self.row = axis(self.row, self.height, row);
+        self.col = axis(self.col, self.width, col);
+        self.clamp(grid_height, grid_width);
+    }
+
+    fn clamp(&mut self, grid_height: usize, grid_width: usize) {
+        self.row = self.row.min(grid_height.saturating_sub(self.height));
+        self.col = self.col.min(grid_width.saturating_sub(self.width));
+    }
+}
+
+/// A key press translated into a playback command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    TogglePause,
+    Step,
+    Faster,
+    Slower,
+    Pan(isize, isize),
+    Follow,
+    Quit,
+}
+
+impl Action {
+    pub fn from_key(code: KeyCode) -> Option<Action> {
+        match code {
+            KeyCode::Char(' ') | KeyCode::Char('p') => Some(Action::TogglePause),
+            KeyCode::Char('n') | KeyCode::Char('.') => Some(Action::Step),
+            KeyCode::Char('+') | KeyCode::Char('=') => Some(Action::Faster),
+            KeyCode::Char('-') => Some(Action::Slower),
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Pan(-1, 0)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Pan(1, 0)),
+            KeyCode::Left | KeyCode::Char('h') => Some(Action::Pan(0, -1)),
+            KeyCode::Right | KeyCode::Char('l') => Some(Action::Pan(0, 1)),
+            KeyCode::Char('f') => Some(Action::Follow),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Draw the part of `grid` inside `viewport` followed by a status line.
+/// Lines end in "\r\n" since the terminal is in raw mode during playback.
+pub fn draw(grid: &Grid<char>, viewport: &Viewport, status: &str) -> String {
+    let mut out = String::from("\x1b[H");
+
+    for row in grid.rows().skip(viewport.row).take(viewport.height) {
+        let mut current = None;
+        for &glyph in row.iter().skip(viewport.col).take(viewport.width) {
+            let color = glyph_color(glyph);
+            if color != current {
+                match color {
+                    Some(code) => write!(out, "\x1b[38;5;{}m", code).unwrap(),
+                    None => out.push_str("\x1b[39m"),
+                }
+                current = color;
+            }
+            out.push(glyph);
+        }
+        out.push_str("\x1b[0m\x1b[K\r\n");
+    }
+
+    write!(out, "\x1b[7m{}\x1b[0m\x1b[K\x1b[J", status).unwrap();
+    out
+}
+
+/// Puts the terminal into raw mode on the alternate screen, restoring it when dropped
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(out: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        write!(out, "\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        out.flush()?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        let _ = write!(out, "\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = out.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Viewport filling the terminal, leaving one row for the status line
+fn terminal_viewport() -> io::Result<Viewport> {
+    let (cols, rows) = terminal::size()?;
+    Ok(Viewport::new(
+        (rows as usize).saturating_sub(1).max(1),
+        (cols as usize).max(1),
+    ))
+}
+
+/// Play `sim` in the terminal until it is quit with 'q' or Esc
+pub fn play(sim: &mut dyn Simulation, options: PlayOptions) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout();
+    if !out.is_terminal() {
+        return Err("playback needs an interactive terminal".into());
+    }
+
+    let _guard = TerminalGuard::enter(&mut out)?;
+    let mut viewport = terminal_viewport()?;
+    let mut speed = options.speed.clamp(MIN_SPEED, MAX_SPEED);
+    let mut paused = options.paused;
+    let mut following = true;
+    let mut finished = false;
+    let mut steps = 0usize;
+    let mut next_tick = Instant::now();
+
+    loop {
+        let frame = sim.frame();
+        if following && let Some(focus) = sim.focus() {
+            viewport.follow(focus, frame.height(), frame.width());
+        }
+
+        let state = if finished {
+            "finished"
+        } else if paused {
+            "paused"
+        } else {
+            "running"
+        };
+        let status = format!(
+            "step {}  {:.1} steps/s  {}  |  {}",
+            steps, speed, state, KEYS
+        );
+        out.write_all(draw(&frame, &viewport, &status).as_bytes())?;
+        out.flush()?;
+
+        // Advance several steps per redraw when running faster than the screen can keep up
+        let batch = (speed / MAX_REDRAWS).ceil().max(1.0);
+        let interval = Duration::from_secs_f64(batch / speed);
+        let timeout = if paused || finished {
+            Duration::from_millis(250)
+        } else {
+            next_tick.saturating_duration_since(Instant::now())
+        };
+
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    match Action::from_key(key.code) {
+                        Some(Action::Quit) => return Ok(()),
+                        Some(Action::TogglePause) => {
+                            paused = !paused;
+                            next_tick = Instant::now();
+                        }
+                        Some(Action::Step) if !finished => {
+                            paused = true;
+                            finished = !sim.step();
+                            steps += usize::from(!finished);
+                        }
+                        Some(Action::Faster) => speed = (speed * 2.0).min(MAX_SPEED),
+                        Some(Action::Slower) => speed = (speed / 2.0).max(MIN_SPEED),
+                        Some(Action::Pan(rows, cols)) => {
+                            following = false;
+                            let (dr, dc) = (
+                                rows * (viewport.height / 4).max(1) as isize,
+                                cols * (viewport.width / 4).max(1) as isize,
+                            );
+                            viewport.pan(dr, dc, frame.height(), frame.width());
+                        }
+                        Some(Action::Follow) => following = true,
+                        _ => {}
+                    }
+                }
+                Event::Resize(_, _) => {
+                    let resized = terminal_viewport()?;
+                    viewport.height = resized.height;
+                    viewport.width = resized.width;
+                    write!(out, "\x1b[2J")?;
+                }
+                _ => {}
+            }
+        } else if !paused && !finished {
+            for _ in 0..batch as usize {
+                if !sim.step() {
+                    finished = true;
+                    break;
+                }
+                steps += 1;
+            }
+            next_tick = Instant::now() + interval;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_clips_to_viewport() {
+        let grid = Grid::parse("#..\n.^.\n..X").unwrap();
+        let viewport = Viewport {
+            row: 1,
+            col: 1,
+            height: 1,
+            width: 2,
+        };
+
+        let out = draw(&grid, &viewport, "step 0");
+        assert!(out.starts_with("\x1b[H"));
+        assert!(out.contains("\x1b[38;5;226m^\x1b[38;5;238m.\x1b[0m\x1b[K\r\n"));
+        assert!(!out.contains('#') && !out.contains('X'));
+        assert!(out.contains("step 0"));
+    }
+
+    #[test]
+    fn test_viewport_follow_and_pan() {
+        let mut viewport = Viewport::new(8, 8);
+        viewport.follow((3, 3), 100, 100);
+        assert_eq!((viewport.row, viewport.col), (0, 0));
+
+        // Leaving the inner region scrolls so the cell sits two cells from the edge
+        viewport.follow((10, 1), 100, 100);
+        assert_eq!((viewport.row, viewport.col), (5, 0));
+
+        viewport.follow((99, 99), 100, 100);
+        assert_eq!((viewport.row, viewport.col), (92, 92));
+
+        viewport.pan(-100, 4, 100, 100);
+        assert_eq!((viewport.row, viewport.col), (0, 92));
+    }
+
+    #[test]
+    fn test_key_actions() {
+        assert_eq!(
+            Action::from_key(KeyCode::Char(' ')),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(Action::from_key(KeyCode::Char('n')), Some(Action::Step));
+        assert_eq!(Action::from_key(KeyCode::Left), Some(Action::Pan(0, -1)));
+        assert_eq!(Action::from_key(KeyCode::Esc), Some(Action::Quit));
+        assert_eq!(Action::from_key(KeyCode::Char('z')), None);
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("2.5"), Ok(2.5));
+        assert_eq!(parse_speed("1e-3"), Ok(0.001));
+        for text in ["0", "-1", "inf", "NaN", "1e400"] {
+            assert_eq!(
+                parse_speed(text),
+                Err(format!(
+                    "expected a finite speed above zero, found {}",
+                    text
+                ))
+            );
+        }
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn test_day06_simulation() {
+        let input = "....#.....\n.........#\n..........\n..#.......\n.......#..\n..........\n.#..^.....\n........#.\n#.........\n......#...";
+        let mut sim = simulation(2024, 6, input).unwrap();
+        let steps = std::iter::from_fn(|| sim.step().then_some(())).count();
+
+        // 44 moves and 10 turns before the guard would step off the map
+        assert_eq!(steps, 54);
+        assert_eq!(sim.frame().iter().filter(|(_, c)| **c == 'X').count(), 40);
+        assert!(simulation(2024, 1, input).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Pure-Rust rendering of character grids to PNG stills and animated GIFs.
+//!
+//! Each grid cell becomes a solid square. Overlays recolour individual cells,
+//! later overlays drawing over earlier ones.
+
+use aoc_common::Grid;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// A colour in 8-bit RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// Distinct colours handed out to overlays in order
+pub const OVERLAY_COLORS: [Rgb; 10] = [
+    Rgb(255, 215, 0),
+    Rgb(0, 200, 255),
+    Rgb(255, 60, 60),
+    Rgb(80, 220, 100),
+    Rgb(255, 120, 220),
+    Rgb(255, 150, 40),
+    Rgb(150, 110, 255),
+    Rgb(0, 160, 140),
+    Rgb(220, 220, 120),
+    Rgb(170, 80, 40),
+];
+
+/// Colour of the `index`-th overlay, cycling through `OVERLAY_COLORS`
+pub fn overlay_color(index: usize) -> Rgb {
+    OVERLAY_COLORS[index % OVERLAY_COLORS.len()]
+}
+
+/// Default colouring of grid characters before overlays are applied
+pub fn default_color(cell: char) -> Rgb {
+    match cell {
+        '.' => Rgb(15, 15, 35),
+        '#' => Rgb(120, 120, 130),
+        '^' | '>' | 'v' | '<' => Rgb(255, 255, 255),
+        _ => Rgb(60, 60, 90),
+    }
+}
+
+/// A set of cells highlighted in one colour
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlay {
+    pub color: Rgb,
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Overlay {
+    pub fn new(color: Rgb, cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        Self {
+            color,
+            cells: cells.into_iter().collect(),
+        }
+    }
+}
+
+/// An image with at most 256 colours, stored as palette indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub palette: Vec<Rgb>,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Palette as packed RGB bytes, as expected by both encoders
+    fn palette_bytes(&self) -> Vec<u8> {
+        self.palette.iter().flat_map(|c| [c.0, c.1, c.2]).collect()
+    }
+
+    /// Encode the image as an indexed-colour PNG
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.palette_bytes());
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)
+    }
+}
+
+/// Encode frames as a looping animated GIF, each shown for `delay` hundredths of a second
+pub fn write_gif<W: Write>(frames: &[Image], delay: u16, writer: W) -> io::Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no frames to encode",
+        ));
+    };
+    let dimension = |value: usize| {
+        u16::try_from(value).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "image is too large for a GIF")
+        })
+    };
+    let (width, height) = (dimension(first.width)?, dimension(first.height)?);
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+
+    for image in frames {
+        let frame = gif::Frame {
+            width: dimension(image.width)?,
+            height: dimension(image.height)?,
+            delay,
+            palette: Some(image.palette_bytes()),
+            buffer: Cow::Borrowed(&image.pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+/// Draws grids as images with a fixed cell size
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    /// Width and height of each cell in pixels
+    pub cell_size: usize,
+    /// Colour of each grid character before overlays
+    pub base_color: fn(char) -> Rgb,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            cell_size: 4,
+            base_color: default_color,
+        }
+    }
+}
+
+impl Renderer {
+    /// Render a grid with overlays drawn in order on top of it.
+    /// Panics if the grid and overlays use more than 256 distinct colours.
+    pub fn render(&self, grid: &Grid<char>, overlays: &[Overlay]) -> Image {
+        let mut palette: Vec<Rgb> = Vec::new();
+        let mut index_of = |color: Rgb| -> u8 {
+            let index = match palette.iter().position(|&c| c == color) {
+                Some(index) => index,
+                None => {
+                    palette.push(color);
+                    palette.len() - 1
+                }
+            };
+            u8::try_from(index).expect("at most 256 colours per image")
+        };
+
+        let mut cells = grid.map(|&cell| index_of((self.base_color)(cell)));
+        for overlay in overlays {
+            let index = index_of(overlay.color);
+            for &(row, col) in &overlay.cells {
+                if let Some(cell) = cells.get_mut(row, col) {
+                    *cell = index;
+                }
+            }
+        }
+
+        let size = self.cell_size;
+        let width = grid.width() * size;
+        let height = grid.height() * size;
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in cells.rows() {
+            for _ in 0..size {
+                for &cell in row {
+                    pixels.extend(std::iter::repeat_n(cell, size));
+                }
+            }
+        }
+
+        Image {
+            width,
+            height,
+            palette,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_pixels() {
+        let grid = Grid::parse("#.\n.^").unwrap();
+        let renderer = Renderer {
+            cell_size: 2,
+            ..Renderer::default()
+        };
+        let image = renderer.render(&grid, &[Overlay::new(overlay_color(0), [(0, 1)])]);
+
+        assert_eq!((image.width, image.height), (4, 4));
+        assert_eq!(
+            image.palette,
+            vec![
+                default_color('#'),
+                default_color('.'),
+                default_color('^'),
+                overlay_color(0)
+            ]
+        );
+        assert_eq!(
+            image.pixels,
+            vec![0, 0, 3, 3, 0, 0, 3, 3, 1, 1, 2, 2, 1, 1, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_encoders() {
+        let grid = Grid::parse("#.\n.^").unwrap();
+        let image = Renderer::default().render(&grid, &[]);
+
+        let mut png_bytes = Vec::new();
+        image.write_png(&mut png_bytes).unwrap();
+        assert_eq!(&png_bytes[1..4], b"PNG");
+
+        let mut gif_bytes = Vec::new();
+        write_gif(&[image.clone(), image], 10, &mut gif_bytes).unwrap();
+        assert_eq!(&gif_bytes[..6], b"GIF89a");
+        assert!(write_gif(&[], 10, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Empirical complexity: time a solver on generated inputs of growing size and fit the curve
+
+use crate::generate;
+use std::error::Error;
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Runs both parts of a day on an input
+pub type Solver = fn(&str);
+
+/// The shape of a bound: a power of `n`, or a constant raised to the `n`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Growth {
+    Polynomial,
+    Exponential,
+}
+
+/// How a day's running time is expected to grow with its generator size `n`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complexity {
+    pub growth: Growth,
+    /// Exponent of `n` in a polynomial bound, or base of an exponential one; log factors
+    /// are left to the tolerance
+    pub exponent: f64,
+    /// The bound as written, such as "n log n"
+    pub label: &'static str,
+    /// What `n` counts in the generator's input
+    pub unit: &'static str,
+}
+
+/// How far the fitted exponent or base may exceed the expected one before warning; this
+/// absorbs log factors and timing noise on small inputs
+pub const TOLERANCE: f64 = 0.35;
+
+macro_rules! both_parts {
+    ($day:ident) => {
+        |input| {
+            black_box($day::part1(black_box(input)));
+            black_box($day::part2(black_box(input)));
+        }
+    };
+}
+
+/// A day's solver and the complexity its algorithm is meant to have
+pub fn solver(year: u16, day: u8) -> Result<(Solver, Complexity), Box<dyn Error>> {
+    let complexity = |exponent, label, unit| Complexity {
+        growth: Growth::Polynomial,
+        exponent,
+        label,
+        unit,
+    };
+    let found: (Solver, Complexity) = match (year, day) {
+        (2024, 1) => (both_parts!(day01), complexity(1.0, "n log n", "lines")),
+        (2024, 2) => (both_parts!(day02), complexity(1.0, "n", "reports")),
+        (2024, 3) => (both_parts!(day03), complexity(1.0, "n", "bytes")),
+        (2024, 4) => (both_parts!(day04), complexity(2.0, "n^2", "grid side")),
+        (2024, 5) => (both_parts!(day05), complexity(1.0, "n", "updates")),
+        // Part 2 only needs to try obstacles on the guard's path and jump from turn to turn;
+        // the current solver tries every cell and walks each one, so this reports it as worse
+        (2024, 6) => (both_parts!(day06), complexity(2.0, "n^2", "grid side")),
+        // Operands per equation are bounded, so the 3^k search is a constant factor here;
+        // the "operands" axis measures it
+        (2024, 7) => (both_parts!(day07), complexity(1.0, "n", "equations")),
+        (2024, 8) => (both_parts!(day08), complexity(2.0, "n^2", "grid side")),
+        _ => return Err(format!("no solver for {} day {}", year, day).into()),
+    };
+    Ok(found)
+}
+
+/// The complexity a day is meant to have along a named axis of
+/// [`generate::axis_generator`]
+pub fn axis_complexity(year: u16, day: u8, axis: &str) -> Result<Complexity, Box<dyn Error>> {
+    match (year, day, axis) {
+        // Each of the n - 1 gaps between operands tries all three operators
+        (2024, 7, "operands") => Ok(Complexity {
+            growth: Growth::Exponential,
+            exponent: 3.0,
+            label: "3^n",
+            unit: "operands",
+        }),
+        _ => Err(format!("no {} axis for {} day {}", axis, year, day).into()),
+    }
+}
+
+/// Settings for a scaling run
+#[derive(Debug, Clone)]
+pub struct ScaleOptions {
+    /// Quantity to grow instead of the day's default size, such as "operands" for day 7
+    pub axis: Option<String>,
+    /// Largest input size; defaults to the size of a real input
+    pub max_size: Option<usize>,
+    /// Number of sizes to time, each double the previous for polynomial bounds and evenly
+    /// spaced for exponential ones
+    pub steps: usize,
+    /// Minimum time spent timing each size; the fastest run is kept
+    pub budget: Duration,
+    pub seed: u64,
+}
+
+/// The fastest run at one input size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub size: usize,
+    pub time: Duration,
+}
+
+/// Timings across sizes with the fitted growth exponent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub samples: Vec<Sample>,
+    /// Fitted exponent, or base if the expected growth is exponential
+    pub exponent: f64,
+    pub expected: Complexity,
+}
+
+impl Report {
+    /// True if the fitted exponent is clearly above the expected one
+    pub fn is_worse_than_expected(&self) -> bool {
+        self.exponent > self.expected.exponent + TOLERANCE
+    }
+
+    /// The fitted bound, such as "O(n^2.1)" or "O(2.7^n)"
+    pub fn estimate(&self) -> String {
+        match self.expected.growth {
+            Growth::Polynomial => format!("O(n^{:.1})", self.exponent),
+            Growth::Exponential => format!("O({:.1}^n)", self.exponent),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>10}  {:>12}", self.expected.unit, "time")?;
+        for sample in &self.samples {
+            writeln!(f, "{:>10}  {:>12.3?}", sample.size, sample.time)?;
+        }
+        write!(
+            f,
+            "estimated {}, expected O({}) for n = {}",
+            self.estimate(),
+            self.expected.label,
+            self.expected.unit
+        )
+    }
+}
+
+/// Least-squares slope of log(time) against log(size), the exponent `k` in `time ~ size^k`
+pub fn fit_exponent(samples: &[Sample]) -> Option<f64> {
+    slope(samples, |size| size.ln())
+}
+
+/// Least-squares slope of log(time) against size, as the base `b` in `time ~ b^size`
+pub fn fit_base(samples: &[Sample]) -> Option<f64> {
+    slope(samples, |size| size).map(f64::exp)
+}
+
+/// Least-squares slope of log(time) against `x` of the size
+fn slope(samples: &[Sample], x: fn(f64) -> f64) -> Option<f64> {
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter(|sample| sample.size > 0 && !sample.time.is_zero())
+        .map(|sample| (x(sample.size as f64), sample.time.as_secs_f64().ln()))
+        .collect();
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+
+    (points.len() >= 2 && variance > 0.0).then(|| covariance / variance)
+}
+
+/// The fastest of repeated runs, repeating until `budget` has passed
+fn time(solve: Solver, input: &str, budget: Duration) -> Duration {
+    let started = Instant::now();
+    let mut fastest = Duration::MAX;
+    let mut runs = 0;
+
+    while runs < 3 || started.elapsed() < budget {
+        let run = Instant::now();
+        solve(input);
+        fastest = fastest.min(run.elapsed());
+        runs += 1;
+    }
+
+    fastest
+}
+
+/// Sizes halving down from `max` for `steps` steps, smallest first
+pub fn sizes(max: usize, steps: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = (0..steps)
+        .map_while(|step| max.checked_shr(step as u32))
+        .filter(|&size| size > 0)
+        .collect();
+    sizes.reverse();
+    sizes.dedup();
+    sizes
+}
+
+/// `steps` evenly spaced sizes up to `max`, smallest first
+pub fn linear_sizes(max: usize, steps: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = (1..=steps)
+        .map(|step| max * step / steps)
+        .filter(|&size| size > 0)
+        .collect();
+    sizes.dedup();
+    sizes
+}
+
+/// Time a day on generated inputs of growing size and fit its growth exponent
+pub fn scale(year: u16, day: u8, options: &ScaleOptions) -> Result<Report, Box<dyn Error>> {
+    let (solve, complexity) = solver(year, day)?;
+    let ((generate, real_size), expected) = match &options.axis {
+        None => (generate::generator(year, day)?, complexity),
+        Some(axis) => (
+            generate::axis_generator(year, day, axis)?,
+            axis_complexity(year, day, axis)?,
+        ),
+    };
+    let max_size = options.max_size.unwrap_or(real_size);
+    let sizes = match expected.growth {
+        Growth::Polynomial => sizes(max_size, options.steps),
+        Growth::Exponential => linear_sizes(max_size, options.steps),
+    };
+
+    let samples: Vec<Sample> = sizes
+        .into_iter()
+        .map(|size| {
+            let input = generate(size, options.seed);
+            Sample {
+                size,
+                time: time(solve, &input, options.budget),
+            }
+        })
+        .collect();
+    let fitted = match expected.growth {
+        Growth::Polynomial => fit_exponent(&samples),
+        Growth::Exponential => fit_base(&samples),
+    };
+    let exponent = fitted.ok_or("need at least two distinct input sizes with measurable times")?;
+
+    Ok(Report {
+        samples,
+        exponent,
+        expected,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(exponent: i32) -> Vec<Sample> {
+        [10, 20, 40, 80]
+            .into_iter()
+            .map(|size: usize| Sample {
+                size,
+                time: Duration::from_nanos(3 * size.pow(exponent as u32) as u64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_exponent() {
+        for exponent in 1..=4 {
+            let fitted = fit_exponent(&samples(exponent)).unwrap();
+            assert!((fitted - exponent as f64).abs() < 1e-6, "{}", fitted);
+        }
+        assert_eq!(fit_exponent(&samples(2)[..1]), None);
+        assert_eq!(sizes(1000, 4), vec![125, 250, 500, 1000]);
+        assert_eq!(sizes(3, 5), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_report_warns_above_expected() {
+        let (_, expected) = solver(2024, 4).unwrap();
+        let mut report = Report {
+            samples: samples(2),
+            exponent: 2.2,
+            expected,
+        };
+        assert!(!report.is_worse_than_expected());
+        assert!(
+            report
+                .to_string()
+                .ends_with("estimated O(n^2.2), expected O(n^2) for n = grid side")
+        );
+
+        report.exponent = 3.0;
+        assert!(report.is_worse_than_expected());
+        assert!(solver(2024, 9).is_err());
+
+        // Day 6's brute force fits about n^2.8 on generated labs
+        report.expected = solver(2024, 6).unwrap().1;
+        report.exponent = 2.8;
+        assert!(report.is_worse_than_expected());
+    }
+
+    #[test]
+    fn test_scale_runs_every_generated_day() {
+        let mut options = ScaleOptions {
+            axis: None,
+            max_size: Some(16),
+            steps: 2,
+            budget: Duration::ZERO,
+            seed: 1,
+        };
+        for day in 1..=8 {
+            let report = scale(2024, day, &options).unwrap();
+            assert_eq!(
+                report.samples.iter().map(|s| s.size).collect::<Vec<_>>(),
+                vec![8, 16]
+            );
+        }
+
+        options.axis = Some("operands".to_string());
+        options.max_size = Some(6);
+        let report = scale(2024, 7, &options).unwrap();
+        assert_eq!(
+            report.samples.iter().map(|s| s.size).collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        assert_eq!(report.expected.growth, Growth::Exponential);
+        assert!(scale(2024, 6, &options).is_err());
+    }
+
+    #[test]
+    fn test_fit_base() {
+        let samples: Vec<Sample> = (2..=6)
+            .map(|size| Sample {
+                size,
+                time: Duration::from_nanos(100 * 3u64.pow(size as u32)),
+            })
+            .collect();
+        let base = fit_base(&samples).unwrap();
+        assert!((base - 3.0).abs() < 1e-3, "{}", base);
+        assert_eq!(linear_sizes(12, 4), vec![3, 6, 9, 12]);
+        assert_eq!(linear_sizes(2, 4), vec![1, 2]);
+
+        let report = Report {
+            samples,
+            exponent: base,
+            expected: axis_complexity(2024, 7, "operands").unwrap(),
+        };
+        assert!(!report.is_worse_than_expected());
+        assert!(
+            report
+                .to_string()
+                .ends_with("estimated O(3.0^n), expected O(3^n) for n = operands")
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Visualisations of the grid puzzles, built on the renderer
+
+use crate::render::{Image, Overlay, Renderer, Rgb, overlay_color};
+use aoc_common::Grid;
+use day04::{Direction, Stencil, Symmetry, X_MAS, find_stencil, find_word};
+use day08::{AntennaMap, AntinodeModel, antinodes, default_frequencies};
+use std::error::Error;
+use std::num::NonZeroUsize;
+
+/// Colour used to redraw antennas and other fixed markers on top of overlays
+const MARKER: Rgb = Rgb(255, 255, 255);
+
+/// Settings shared by all visualisations
+#[derive(Debug, Clone, Copy)]
+pub struct VizOptions {
+    /// Upper bound on the number of frames for animated days
+    pub frames: NonZeroUsize,
+    pub renderer: Renderer,
+}
+
+/// Render the frames for a given puzzle. Stills are returned as a single frame.
+pub fn visualise(
+    year: u16,
+    day: u8,
+    input: &str,
+    options: &VizOptions,
+) -> Result<Vec<Image>, Box<dyn Error>> {
+    match (year, day) {
+        (2024, 4) => day04_frames(input, options),
+        (2024, 6) => day06_frames(input, options),
+        (2024, 8) => day08_frames(input, options),
+        _ => Err(format!("no visualisation for {} day {}", year, day).into()),
+    }
+}
+
+/// XMAS words in the first frame and X-MAS crosses in the second
+fn day04_frames(input: &str, options: &VizOptions) -> Result<Vec<Image>, Box<dyn Error>> {
+    let rows = day04::parse_grid(input)?;
+
+    let word_cells: Vec<(usize, usize)> = find_word(&rows, "XMAS", &Direction::ALL)
+        .iter()
+        .flat_map(|m| {
+            let (di, dj) = m.direction.delta();
+            (0..4).map(move |k| {
+                (
+                    (m.row as isize + di * k) as usize,
+                    (m.col as isize + dj * k) as usize,
+                )
+            })
+        })
+        .collect();
+
+    let variants = Stencil::parse(X_MAS).variants(Symmetry::Rotations);
+    let cross_cells: Vec<(usize, usize)> =
+        find_stencil(&rows, &Stencil::parse(X_MAS), Symmetry::Rotations)
+            .iter()
+            .flat_map(|m| {
+                variants[m.variant]
+                    .fixed_cells()
+                    .into_iter()
+                    .map(move |(di, dj)| (m.row + di, m.col + dj))
+            })
+            .collect();
+
+    let grid = Grid::from_rows(rows).expect("parse_grid rejects ragged rows");
+    let renderer = options.renderer;
+    Ok(vec![
+        renderer.render(&grid, &[Overlay::new(overlay_color(0), word_cells)]),
+        renderer.render(&grid, &[Overlay::new(overlay_color(1), cross_cells)]),
+    ])
+}
+
+/// The guard walking its path, ending with the obstacles that would trap it in a loop
+fn day06_frames(input: &str, options: &VizOptions) -> Result<Vec<Image>, Box<dyn Error>> {
+    let grid = Grid::parse(input)?;
+    let path = day06::guard_path(input)?;
+    let obstacles = day06::loop_obstacles(input)?;
+    let renderer = options.renderer;
+
+    // One frame is kept for the obstacles at the end
+    let walk_frames = options.frames.get() - 1;
+    let step = path.len().div_ceil(walk_frames.max(1)).max(1);
+    let path_overlay = |end: usize| Overlay::new(overlay_color(0), path[..end].iter().copied());
+
+    let mut frames: Vec<Image> = (1..=path.len().div_ceil(step).min(walk_frames))
+        .map(|k| renderer.render(&grid, &[path_overlay((k * step).min(path.len()))]))
+        .collect();
+    frames.push(renderer.render(
+        &grid,
+        &[
+            path_overlay(path.len()),
+            Overlay::new(overlay_color(2), obstacles),
+        ],
+    ));
+
+    Ok(frames)
+}
+
+/// Antinodes coloured by frequency: nearest antinodes first, then all harmonics
+fn day08_frames(input: &str, options: &VizOptions) -> Result<Vec<Image>, Box<dyn Error>> {
+    let grid = Grid::parse(input)?;
+    let map = AntennaMap::parse(input, &default_frequencies())?;
+    let antennas = Overlay::new(MARKER, map.antennas.values().flatten().copied());
+
+    let frame = |model| {
+        let mut overlays: Vec<Overlay> = antinodes(&map, model)
+            .values()
+            .enumerate()
+            .map(|(index, cells)| Overlay::new(overlay_color(index), cells.iter().copied()))
+            .collect();
+        overlays.push(antennas.clone());
+        options.renderer.render(&grid, &overlays)
+    };
+
+    Ok(vec![
+        frame(AntinodeModel::Nearest),
+        frame(AntinodeModel::Harmonics),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY04_EXAMPLE: &str = "MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    const DAY06_EXAMPLE: &str = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+
+    const DAY08_EXAMPLE: &str = "............
+........0...
+.....0......
+.......0....
+....0.......
+......A.....
+............
+............
+........A...
+.........A..
+............
+............";
+
+    /// FNV-1a over every frame's size, palette and pixels; stable across platforms
+    /// and independent of the PNG/GIF encoder versions
+    fn snapshot_hash(frames: &[Image]) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for &b in bytes {
+                hash ^= u64::from(b);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        for frame in frames {
+            feed(&(frame.width as u64).to_le_bytes());
+            feed(&(frame.height as u64).to_le_bytes());
+            for color in &frame.palette {
+                feed(&[color.0, color.1, color.2]);
+            }
+            feed(&frame.pixels);
+        }
+
+        hash
+    }
+
+    fn render(day: u8, input: &str) -> Vec<Image> {
+        let options = VizOptions {
+            frames: NonZeroUsize::new(10).unwrap(),
+            renderer: Renderer::default(),
+        };
+        visualise(2024, day, input, &options).unwrap()
+    }
+
+    #[test]
+    fn test_day04_snapshot() {
+        let frames = render(4, DAY04_EXAMPLE);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(snapshot_hash(&frames), 6835270503249970146);
+    }
+
+    #[test]
+    fn test_day06_snapshot() {
+        let frames = render(6, DAY06_EXAMPLE);
+        assert_eq!(frames.len(), 10);
+        assert_eq!(snapshot_hash(&frames), 18402737461789694510);
+    }
+
+    #[test]
+    fn test_day06_frame_limit() {
+        for limit in [1, 2, 3, 10, 100] {
+            let options = VizOptions {
+                frames: NonZeroUsize::new(limit).unwrap(),
+                renderer: Renderer::default(),
+            };
+            let frames = visualise(2024, 6, DAY06_EXAMPLE, &options).unwrap();
+            assert!(
+                frames.len() <= limit,
+                "{} frames for a limit of {}",
+                frames.len(),
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_day08_snapshot() {
+        let frames = render(8, DAY08_EXAMPLE);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(snapshot_hash(&frames), 15543272635798247131);
+    }
+
+    #[test]
+    fn test_unsupported_day() {
+        let options = VizOptions {
+            frames: NonZeroUsize::new(10).unwrap(),
+            renderer: Renderer::default(),
+        };
+        assert!(visualise(2024, 1, "", &options).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use aoc_common::ints::{fixed_uint, iter_uints, parse_int, uints};
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::fmt::Write;
+
+/// Lines of two five-digit IDs, the layout of a day 1 input
+fn generate_input(lines: usize) -> String {
+    let mut state: u64 = 0x2024_0045;
+    let mut next = || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        10_000 + state % 90_000
+    };
+
+    let mut input = String::with_capacity(lines * 14);
+    for _ in 0..lines {
+        writeln!(input, "{}   {}", next(), next()).unwrap();
+    }
+
+    input
+}
+
+fn bench_ints(c: &mut Criterion) {
+    let input = generate_input(100_000);
+    let mut group = c.benchmark_group("ints");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("str_parse", |b| {
+        b.iter(|| {
+            black_box(&input)
+                .split_whitespace()
+                .map(|token| token.parse::<u32>().unwrap())
+                .fold(0u64, |sum, n| sum + u64::from(n))
+        })
+    });
+    group.bench_function("parse_int", |b| {
+        b.iter(|| {
+            black_box(&input)
+                .split_ascii_whitespace()
+                .map(|token| parse_int::<u32>(token).unwrap())
+                .fold(0u64, |sum, n| sum + u64::from(n))
+        })
+    });
+    group.bench_function("fixed_uint", |b| {
+        b.iter(|| {
+            black_box(&input)
+                .as_bytes()
+                .chunks(14)
+                .map(|line| {
+                    fixed_uint::<u32, 5>(line[..5].try_into().unwrap()).unwrap()
+                        + fixed_uint::<u32, 5>(line[8..13].try_into().unwrap()).unwrap()
+                })
+                .fold(0u64, |sum, n| sum + u64::from(n))
+        })
+    });
+    group.bench_function("iter_uints", |b| {
+        b.iter(|| {
+            iter_uints::<u32>(black_box(&input)).fold(0u64, |sum, n| sum + u64::from(n.unwrap()))
+        })
+    });
+    group.bench_function("uints", |b| {
+        b.iter(|| uints::<u32>(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_ints);
+criterion_main!(benches);
@@ -0,0 +1,414 @@
+//! Line-by-line parsing and the runtime side of `#[derive(AocParse)]`
+
+use crate::parse::span_of;
+use crate::{ParseError, ParseMode, Parsed};
+use std::str::FromStr;
+
+/// Parse every non-empty line of `input` as a `T`, rejecting any malformed line
+pub fn parse_lines<T>(input: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr<Err = ParseError>,
+{
+    parse_lines_with(input, ParseMode::Strict).map(|parsed| parsed.items)
+}
+
+/// Parse every non-empty line of `input` as a `T`, handling malformed lines according to `mode`
+pub fn parse_lines_with<T>(input: &str, mode: ParseMode) -> Result<Parsed<T>, ParseError>
+where
+    T: FromStr<Err = ParseError>,
+{
+    parse_lines_by(input, mode, str::parse)
+}
+
+/// Parse every non-empty line of `input` with `parse`, handling malformed lines according
+/// to `mode`. `parse` reports errors relative to the line.
+pub fn parse_lines_by<T, F>(input: &str, mode: ParseMode, parse: F) -> Result<Parsed<T>, ParseError>
+where
+    F: Fn(&str) -> Result<T, ParseError>,
+{
+    let mut items = Vec::new();
+    let mut skipped = 0;
+
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        match (parse(line), mode) {
+            (Ok(item), _) => items.push(item),
+            (Err(_), ParseMode::Lenient) => skipped += 1,
+            (Err(err), ParseMode::Strict) => return Err(locate(input, line, err)),
+        }
+    }
+
+    Ok(Parsed { items, skipped })
+}
+
+/// Move an error found in `line`, a slice of `input`, to its place in `input`
+fn locate(input: &str, line: &str, err: ParseError) -> ParseError {
+    let start = span_of(input, line).start;
+    err.offset_by(input[..start].matches('\n').count(), start)
+}
+
+/// A block of consecutive non-blank lines, remembering where it sits in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// The lines of the section, without the blank lines around it
+    pub text: &'a str,
+    /// Line number of the first line within the whole input, starting at 1
+    pub line: usize,
+    /// Byte offset of the first line within the whole input
+    pub offset: usize,
+}
+
+impl<'a> Section<'a> {
+    /// The lines of the section, none of them blank
+    pub fn lines(&self) -> std::str::Lines<'a> {
+        self.text.lines()
+    }
+
+    /// Move an error located relative to the section to its place in the whole input
+    pub fn locate(&self, err: ParseError) -> ParseError {
+        err.offset_by(self.line - 1, self.offset)
+    }
+
+    /// Error covering `part`, which must be a slice of the section
+    pub fn error_at(&self, part: &str, message: impl Into<String>) -> ParseError {
+        self.locate(ParseError::at(self.text, part, message))
+    }
+
+    /// Parse every line of the section as a `T`, locating errors within the whole input
+    pub fn parse_lines<T>(&self) -> Result<Vec<T>, ParseError>
+    where
+        T: FromStr<Err = ParseError>,
+    {
+        self.parse_lines_by(str::parse)
+    }
+
+    /// Parse every line of the section with `parse`, locating errors within the whole input
+    pub fn parse_lines_by<T, F>(&self, parse: F) -> Result<Vec<T>, ParseError>
+    where
+        F: Fn(&str) -> Result<T, ParseError>,
+    {
+        parse_lines_by(self.text, ParseMode::Strict, parse)
+            .map(|parsed| parsed.items)
+            .map_err(|err| self.locate(err))
+    }
+}
+
+/// Split `input` into sections separated by one or more blank lines
+pub fn sections(input: &str) -> Vec<Section<'_>> {
+    let mut sections = Vec::new();
+    let mut current: Option<(usize, usize, usize)> = None;
+
+    for (index, line) in input.lines().enumerate() {
+        let span = span_of(input, line);
+        if line.trim().is_empty() {
+            sections.extend(current.take());
+        } else {
+            let (first, start) =
+                current.map_or((index + 1, span.start), |(first, start, _)| (first, start));
+            current = Some((first, start, span.end));
+        }
+    }
+    sections.extend(current);
+
+    sections
+        .into_iter()
+        .map(|(line, start, end)| Section {
+            text: &input[start..end],
+            line,
+            offset: start,
+        })
+        .collect()
+}
+
+/// Split `input` into `required` to `count` sections separated by blank lines
+#[doc(hidden)]
+pub fn split_sections(
+    input: &str,
+    required: usize,
+    count: usize,
+) -> Result<Vec<Section<'_>>, ParseError> {
+    let sections = sections(input);
+    let expected = if required == count {
+        plural(count, "section")
+    } else {
+        format!("at most {}", plural(count, "section"))
+    };
+
+    if let Some(extra) = sections.get(count) {
+        return Err(extra.error_at(
+            extra.text,
+            format!("expected {} separated by blank lines, found more", expected),
+        ));
+    }
+    if sections.len() < required {
+        return Err(ParseError::new(
+            input,
+            input.len()..input.len(),
+            format!(
+                "expected {} separated by blank lines, found {}",
+                plural(required, "section"),
+                sections.len()
+            ),
+        ));
+    }
+
+    Ok(sections)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+/// One piece of a line format generated by `#[derive(AocParse)]`. Fields carry the
+/// label that names their values in error messages.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// Text that must appear, with flexible whitespace around it
+    Literal(&'static str),
+    /// A single whitespace-free value, or everything up to the next literal
+    Value(&'static str),
+    /// Everything up to the next literal or the end of the line
+    List(&'static str),
+}
+
+/// The whitespace-free token at the start of `text`, empty at the end of a line
+fn first_token(text: &str) -> &str {
+    &text[..text.find(char::is_whitespace).unwrap_or(text.len())]
+}
+
+/// Match a line against a format, returning the text of each field in order.
+/// Errors are located relative to the line.
+#[doc(hidden)]
+pub fn match_line<'a>(line: &'a str, segments: &[Segment]) -> Result<Vec<&'a str>, ParseError> {
+    let line = line.trim_end();
+    let skip_whitespace = |pos: usize| line.len() - line[pos..].trim_start().len();
+    let mut pos = 0;
+    let mut fields = Vec::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        pos = skip_whitespace(pos);
+        let rest = &line[pos..];
+
+        match *segment {
+            Segment::Literal(literal) => {
+                let literal = literal.trim();
+                if !rest.starts_with(literal) {
+                    let found = first_token(rest);
+                    let message = match found {
+                        "" => format!("expected '{}' at the end of the line", literal),
+                        found => format!("expected '{}', found '{}'", literal, found),
+                    };
+                    return Err(ParseError::at(line, found, message));
+                }
+                pos += literal.len();
+            }
+            Segment::Value(label) | Segment::List(label) => {
+                let next = match segments.get(index + 1) {
+                    Some(Segment::Literal(literal)) => literal.trim(),
+                    _ => "",
+                };
+                let len = if !next.is_empty() {
+                    // Point at everything that should have held the value and the literal
+                    rest.find(next).ok_or_else(|| {
+                        let message = match rest {
+                            "" => format!("missing {}", label),
+                            _ => format!("expected '{}' after the {}", next, label),
+                        };
+                        ParseError::at(line, rest, message)
+                    })?
+                } else if matches!(segment, Segment::List(_)) {
+                    rest.len()
+                } else {
+                    first_token(rest).len()
+                };
+
+                let text = rest[..len].trim_end();
+                if text.is_empty() && matches!(segment, Segment::Value(_)) {
+                    return Err(ParseError::at(line, text, format!("missing {}", label)));
+                }
+                fields.push(text);
+                pos += len;
+            }
+        }
+    }
+
+    let rest = &line[skip_whitespace(pos)..];
+    if !rest.is_empty() {
+        return Err(ParseError::at(
+            line,
+            rest,
+            format!("unexpected trailing text '{}'", rest),
+        ));
+    }
+
+    Ok(fields)
+}
+
+/// Convert the text of a field, pointing at it on failure
+#[doc(hidden)]
+pub fn parse_value<T: FromStr>(line: &str, text: &str, label: &str) -> Result<T, ParseError> {
+    text.parse()
+        .map_err(|_| ParseError::at(line, text, format!("invalid {} '{}'", label, text)))
+}
+
+/// Split the text of a list field on `sep` and convert each value.
+/// A blank separator splits on any run of whitespace.
+#[doc(hidden)]
+pub fn parse_list<T: FromStr>(
+    line: &str,
+    text: &str,
+    sep: &str,
+    min: usize,
+    label: &str,
+) -> Result<Vec<T>, ParseError> {
+    let values: Vec<&str> = if text.is_empty() {
+        Vec::new()
+    } else if sep.trim().is_empty() {
+        text.split_whitespace().collect()
+    } else {
+        text.split(sep).map(str::trim).collect()
+    };
+
+    if values.len() < min {
+        let plural = if min == 1 { "" } else { "s" };
+        return Err(ParseError::at(
+            line,
+            text,
+            format!("expected at least {} {}{}", min, label, plural),
+        ));
+    }
+
+    values
+        .into_iter()
+        .map(|value| parse_value(line, value, label))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AocParse;
+
+    #[derive(Debug, PartialEq, AocParse)]
+    #[aoc(format = "{target}: {operands: sep=' ', min=1, label='operand'}")]
+    struct Equation {
+        target: u64,
+        operands: Vec<u64>,
+    }
+
+    #[derive(Debug, PartialEq, AocParse)]
+    #[aoc(format = "p={x},{y} {{{name}}}")]
+    struct Point {
+        x: i32,
+        y: i32,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, AocParse)]
+    #[aoc(sections)]
+    struct Manual {
+        points: Vec<Point>,
+        equations: Vec<Equation>,
+    }
+
+    #[derive(Debug, PartialEq, AocParse)]
+    #[aoc(sections)]
+    struct Optional {
+        points: Vec<Point>,
+        #[aoc(default)]
+        equations: Vec<Equation>,
+    }
+
+    #[test]
+    fn test_line_formats() {
+        assert_eq!(
+            "3267:  81 40 27".parse(),
+            Ok(Equation {
+                target: 3267,
+                operands: vec![81, 40, 27]
+            })
+        );
+        assert_eq!(
+            "p=-3, 4 {origin}".parse(),
+            Ok(Point {
+                x: -3,
+                y: 4,
+                name: "origin".to_string()
+            })
+        );
+
+        let err = "190: 10 x9".parse::<Equation>().unwrap_err();
+        assert_eq!((err.column, err.span.clone()), (9, 8..10));
+        assert_eq!(err.message, "invalid operand 'x9'");
+
+        let err = "190 10".parse::<Equation>().unwrap_err();
+        assert_eq!((err.column, err.span.clone()), (1, 0..6));
+        assert_eq!(err.message, "expected ':' after the target");
+        let err = "190:".parse::<Equation>().unwrap_err();
+        assert_eq!(
+            (err.column, err.message.as_str()),
+            (5, "expected at least 1 operand")
+        );
+
+        let err = "q=1,2 {a}".parse::<Point>().unwrap_err();
+        assert_eq!(
+            (err.span.clone(), err.message.as_str()),
+            (0..5, "expected 'p=', found 'q=1,2'")
+        );
+        let err = "p=1,2 {a".parse::<Point>().unwrap_err();
+        assert_eq!(
+            (err.span.clone(), err.message.as_str()),
+            (7..8, "expected '}' after the name")
+        );
+        let err = "".parse::<Point>().unwrap_err();
+        assert_eq!(
+            (err.span.clone(), err.message.as_str()),
+            (0..0, "expected 'p=' at the end of the line")
+        );
+        assert_eq!(
+            "p=1,2 {a} 3".parse::<Point>().unwrap_err().message,
+            "unexpected trailing text '3'"
+        );
+    }
+
+    #[test]
+    fn test_sections_and_lines() {
+        let input = "p=1,2 {a}\n\n\n5: 1 4\n6: 2 3\n";
+        let manual: Manual = input.parse().unwrap();
+        assert_eq!(manual.points.len(), 1);
+        assert_eq!(manual.equations[1].operands, vec![2, 3]);
+
+        let err = "p=1,2 {a}\n\n5: 1 4\n6: 2 y".parse::<Manual>().unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (4, 6, 23..24));
+        let err = "p=1,2 {a}".parse::<Manual>().unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected 2 sections separated by blank lines, found 1"
+        );
+
+        let optional: Optional = "p=1,2 {a}\n\n".parse().unwrap();
+        assert_eq!((optional.points.len(), optional.equations.len()), (1, 0));
+        let err = "\n".parse::<Optional>().unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected 1 section separated by blank lines, found 0"
+        );
+        let err = "p=1,2 {a}\n\n5: 1\n\n6: 2".parse::<Optional>().unwrap_err();
+        assert_eq!((err.line, err.span.clone()), (5, 17..21));
+        assert_eq!(
+            err.message,
+            "expected at most 2 sections separated by blank lines, found more"
+        );
+
+        let blocks = sections("\n\na|b\nc\n \n\nd\n");
+        let starts: Vec<(usize, usize)> = blocks.iter().map(|s| (s.line, s.offset)).collect();
+        assert_eq!(starts, vec![(3, 2), (7, 11)]);
+        assert_eq!(blocks[0].lines().collect::<Vec<_>>(), vec!["a|b", "c"]);
+        let err = blocks[1].error_at(blocks[1].text, "unexpected");
+        assert_eq!((err.line, err.column, err.span), (7, 1, 11..12));
+
+        let parsed = parse_lines_with::<Equation>("1: 1\nbad\n2: 2", ParseMode::Lenient).unwrap();
+        assert_eq!((parsed.items.len(), parsed.skipped), (2, 1));
+        assert_eq!(parse_lines::<Equation>("1: 1\nbad").unwrap_err().line, 2);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::ParseError;
+use std::fmt;
+
+/// Problems found while reading a grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridError {
+    /// A row whose length differs from the first row
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} has {} columns, expected {}",
+                row, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// A rectangular 2D grid stored row by row
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T = char> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// A grid of the given size with every cell set to `fill`
+    pub fn new(width: usize, height: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Build a grid from rows, which must all have the same length
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, GridError> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        let mut cells = Vec::with_capacity(width * height);
+
+        for (row, cells_in_row) in rows.into_iter().enumerate() {
+            if cells_in_row.len() != width {
+                return Err(GridError::RaggedRow {
+                    row,
+                    expected: width,
+                    found: cells_in_row.len(),
+                });
+            }
+            cells.extend(cells_in_row);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    /// Number of columns
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether (row, col) lies inside the grid
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row < self.height && col < self.width
+    }
+
+    /// The cell at (row, col), or None if out of bounds
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        self.contains(row, col)
+            .then(|| &self.cells[row * self.width + col])
+    }
+
+    /// Mutable access to the cell at (row, col), or None if out of bounds
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if self.contains(row, col) {
+            Some(&mut self.cells[row * self.width + col])
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over rows as slices
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks panics on a zero chunk size, which an empty-width grid would give
+        self.cells.chunks(self.width.max(1)).take(self.height)
+    }
+
+    /// Iterate over every cell with its (row, col) position
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, cell)| ((index / width, index % width), cell))
+    }
+
+    /// Apply `f` to every cell, keeping the shape
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+/// Split a character grid into rows, skipping blank lines. Every row must have
+/// the same number of characters as the first.
+pub fn parse_rows(input: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    let mut rows: Vec<Vec<char>> = Vec::new();
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let row: Vec<char> = line.chars().collect();
+        if let Some(first) = rows.first()
+            && row.len() != first.len()
+        {
+            return Err(ParseError::at(
+                input,
+                line,
+                format!("row has {} columns, expected {}", row.len(), first.len()),
+            ));
+        }
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+impl Grid<char> {
+    /// Parse a character grid, skipping blank lines
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Ok(Self::from_rows(parse_rows(input)?).expect("rows have equal length"))
+    }
+}
+
+impl fmt::Display for Grid<char> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, row) in self.rows().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_access() {
+        let mut grid = Grid::parse("ab.\n.#c\n").unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.get(1, 1), Some(&'#'));
+        assert_eq!(grid.get(2, 0), None);
+
+        *grid.get_mut(0, 2).unwrap() = 'x';
+        assert_eq!(grid.to_string(), "abx\n.#c");
+        assert_eq!(grid.iter().filter(|(_, c)| **c == '.').count(), 1);
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        assert_eq!(
+            Grid::from_rows(vec![vec!['a', 'b', 'c'], vec!['d', 'e']]),
+            Err(GridError::RaggedRow {
+                row: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+
+        let err = Grid::parse("abc\n\nde").unwrap_err();
+        assert_eq!((err.line, err.column, err.span.clone()), (3, 1, 5..7));
+        assert_eq!(err.message, "row has 2 columns, expected 3");
+    }
+}
@@ -0,0 +1,335 @@
+//! Fast integer parsing and extraction without going through `str::parse`
+
+use crate::ParseError;
+use std::any::type_name;
+use std::marker::PhantomData;
+
+/// Primitive integer types that can be built from parsed digits
+pub trait Int: Copy {
+    /// Whether a leading '-' is part of the number
+    const SIGNED: bool;
+
+    /// The value with the given magnitude and sign, or None if it does not fit
+    fn from_magnitude(magnitude: u128, negative: bool) -> Option<Self>;
+}
+
+macro_rules! impl_int {
+    ($signed:literal => $($ty:ty),*) => {$(
+        impl Int for $ty {
+            const SIGNED: bool = $signed;
+
+            #[inline]
+            fn from_magnitude(magnitude: u128, negative: bool) -> Option<Self> {
+                if negative {
+                    // 2^127 is the magnitude of i128::MIN, which has no positive counterpart
+                    let value = match magnitude {
+                        m if m <= 1 << 127 => (m as i128).wrapping_neg(),
+                        _ => return None,
+                    };
+                    Self::try_from(value).ok()
+                } else {
+                    Self::try_from(magnitude).ok()
+                }
+            }
+        }
+    )*};
+}
+
+impl_int!(true => i8, i16, i32, i64, i128, isize);
+impl_int!(false => u8, u16, u32, u64, u128, usize);
+
+const ZEROS: u64 = 0x3030_3030_3030_3030;
+const HIGH_NIBBLES: u64 = 0xF0F0_F0F0_F0F0_F0F0;
+
+/// Value of eight ASCII digits, checking and combining them as one `u64`
+#[inline]
+fn swar_digits(digits: [u8; 8]) -> Option<u64> {
+    let chunk = u64::from_le_bytes(digits);
+
+    // Each byte must be 0x30..=0x39: a high nibble of 3 both before and after adding 6
+    if chunk & HIGH_NIBBLES != ZEROS
+        || chunk.wrapping_add(0x0606_0606_0606_0606) & HIGH_NIBBLES != ZEROS
+    {
+        return None;
+    }
+
+    // The first digit is in the lowest byte; merge neighbours into pairs, quads, then all eight
+    let mut value = chunk - ZEROS;
+    value = (value * 10 + (value >> 8)) & 0x00FF_00FF_00FF_00FF;
+    value = (value * 100 + (value >> 16)) & 0x0000_FFFF_0000_FFFF;
+    value = (value * 10_000 + (value >> 32)) & 0xFFFF_FFFF;
+    Some(value)
+}
+
+/// Value of a non-empty run of ASCII digits, or None if anything else appears or it overflows
+#[inline]
+fn magnitude(digits: &[u8]) -> Option<u128> {
+    if digits.is_empty() {
+        return None;
+    }
+
+    // Up to 19 digits always fit in a u64; only wider numbers need checked u128 arithmetic
+    if digits.len() <= 19 {
+        let mut chunks = digits.chunks_exact(8);
+        let mut value = 0u64;
+        for chunk in &mut chunks {
+            value = value * 100_000_000 + swar_digits(chunk.try_into().unwrap())?;
+        }
+        for &b in chunks.remainder() {
+            let digit = b.wrapping_sub(b'0');
+            if digit > 9 {
+                return None;
+            }
+            value = value * 10 + u64::from(digit);
+        }
+        return Some(u128::from(value));
+    }
+
+    digits.iter().try_fold(0u128, |value, &b| {
+        let digit = b.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        value.checked_mul(10)?.checked_add(u128::from(digit))
+    })
+}
+
+/// Parse a field of known width made only of ASCII digits, such as the five-digit
+/// location IDs of day 1. Fields of exactly eight digits are converted in one step.
+#[inline]
+pub fn fixed_uint<T: Int, const N: usize>(digits: &[u8; N]) -> Option<T> {
+    let value = if N == 8 {
+        u128::from(swar_digits(digits[..].try_into().unwrap())?)
+    } else {
+        magnitude(digits)?
+    };
+    T::from_magnitude(value, false)
+}
+
+/// Parse a whole token as an integer with an optional sign, like `str::parse`
+/// but without its error type. Returns None for anything else.
+#[inline]
+pub fn parse_int<T: Int>(token: &str) -> Option<T> {
+    let bytes = token.as_bytes();
+    let (negative, digits) = match bytes.first() {
+        Some(b'-') if T::SIGNED => (true, &bytes[1..]),
+        Some(b'+') => (false, &bytes[1..]),
+        _ => (false, bytes),
+    };
+    T::from_magnitude(magnitude(digits)?, negative)
+}
+
+/// Parse `text` as integers separated by the byte `sep` in a single pass, where a space
+/// separator accepts any run of spaces. Returns None if anything else appears, so callers
+/// can fall back to a parser that explains the problem.
+pub fn split_ints<T: Int>(text: &str, sep: u8) -> Option<Vec<T>> {
+    let bytes = text.as_bytes();
+    let mut values = Vec::with_capacity(bytes.len() / 2 + 1);
+    let mut pos = 0;
+
+    loop {
+        if sep == b' ' {
+            while bytes.get(pos) == Some(&b' ') {
+                pos += 1;
+            }
+            if pos == bytes.len() {
+                break;
+            }
+        }
+
+        let negative = T::SIGNED && bytes.get(pos) == Some(&b'-');
+        let start = pos + usize::from(negative);
+        let mut value = 0u64;
+        pos = start;
+        while let Some(digit) = bytes.get(pos).map(|b| b.wrapping_sub(b'0')) {
+            if digit > 9 {
+                break;
+            }
+            value = value.wrapping_mul(10).wrapping_add(u64::from(digit));
+            pos += 1;
+        }
+
+        // The running value is exact for up to 19 digits
+        let abs = match pos - start {
+            0 => return None,
+            1..=19 => u128::from(value),
+            _ => magnitude(&bytes[start..pos])?,
+        };
+        values.push(T::from_magnitude(abs, negative)?);
+
+        match bytes.get(pos) {
+            None => break,
+            Some(&b) if b == sep => pos += 1,
+            Some(_) => return None,
+        }
+    }
+
+    Some(values)
+}
+
+/// Iterator over the integers embedded in a string, ignoring any other text
+pub struct Ints<'a, T> {
+    input: &'a str,
+    pos: usize,
+    signed: bool,
+    marker: PhantomData<T>,
+}
+
+impl<T: Int> Iterator for Ints<'_, T> {
+    type Item = Result<T, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.input.as_bytes();
+        let offset = bytes[self.pos..].iter().position(u8::is_ascii_digit)?;
+        let mut start = self.pos + offset;
+        let end = bytes[start..]
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .map_or(bytes.len(), |len| start + len);
+
+        // A '-' is a sign only when it does not join two numbers, as in "3-5"
+        let negative = self.signed
+            && start > self.pos
+            && bytes[start - 1] == b'-'
+            && (start < 2 || !bytes[start - 2].is_ascii_digit());
+        if negative {
+            start -= 1;
+        }
+        self.pos = end;
+
+        let text = &self.input[start..end];
+        let value = magnitude(&bytes[start + usize::from(negative)..end])
+            .and_then(|magnitude| T::from_magnitude(magnitude, negative));
+        Some(value.ok_or_else(|| {
+            ParseError::new(
+                self.input,
+                start..end,
+                format!("number '{}' out of range for {}", text, type_name::<T>()),
+            )
+        }))
+    }
+}
+
+/// Iterate over the integers in `input`, treating a '-' right before a number as its sign
+pub fn iter_ints<T: Int>(input: &str) -> Ints<'_, T> {
+    Ints {
+        input,
+        pos: 0,
+        signed: T::SIGNED,
+        marker: PhantomData,
+    }
+}
+
+/// Iterate over the runs of digits in `input`, ignoring any '-'
+pub fn iter_uints<T: Int>(input: &str) -> Ints<'_, T> {
+    Ints {
+        signed: false,
+        ..iter_ints(input)
+    }
+}
+
+/// Every integer in `input`, treating a '-' right before a number as its sign
+pub fn ints<T: Int>(input: &str) -> Result<Vec<T>, ParseError> {
+    iter_ints(input).collect()
+}
+
+/// Every run of digits in `input` as a number, ignoring any '-'
+pub fn uints<T: Int>(input: &str) -> Result<Vec<T>, ParseError> {
+    iter_uints(input).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int_matches_str_parse() {
+        for token in [
+            "0",
+            "7",
+            "-7",
+            "+7",
+            "12345",
+            "99999999",
+            "123456789",
+            "-2147483648",
+            "2147483648",
+            "",
+            "-",
+            "1 ",
+            "1a",
+            "٣",
+            "00000000000000000042",
+        ] {
+            assert_eq!(
+                parse_int::<i32>(token),
+                token.parse::<i32>().ok(),
+                "{:?}",
+                token
+            );
+            assert_eq!(
+                parse_int::<u64>(token),
+                token.parse::<u64>().ok(),
+                "{:?}",
+                token
+            );
+        }
+        assert_eq!(parse_int::<i128>(&i128::MIN.to_string()), Some(i128::MIN));
+        assert_eq!(parse_int::<u128>(&u128::MAX.to_string()), Some(u128::MAX));
+        assert_eq!(parse_int::<u8>("256"), None);
+    }
+
+    #[test]
+    fn test_fixed_uint() {
+        assert_eq!(fixed_uint::<u32, 5>(b"38665"), Some(38665));
+        assert_eq!(fixed_uint::<u32, 8>(b"00000001"), Some(1));
+        assert_eq!(fixed_uint::<u32, 8>(b"1234567:"), None);
+        assert_eq!(fixed_uint::<u32, 8>(b"/2345678"), None);
+        assert_eq!(fixed_uint::<u8, 8>(b"00000256"), None);
+        assert_eq!(
+            fixed_uint::<u64, 13>(b"1234567890123"),
+            Some(1_234_567_890_123)
+        );
+    }
+
+    #[test]
+    fn test_split_ints() {
+        assert_eq!(
+            split_ints::<i32>("75,-47,61", b','),
+            Some(vec![75, -47, 61])
+        );
+        assert_eq!(
+            split_ints::<u64>("  81 40   27 ", b' '),
+            Some(vec![81, 40, 27])
+        );
+        assert_eq!(
+            split_ints::<u128>("123456789012345678901234567890", b' '),
+            Some(vec![123_456_789_012_345_678_901_234_567_890])
+        );
+        assert_eq!(split_ints::<u8>("", b' '), Some(vec![]));
+        for (text, sep) in [
+            ("1,,2", b','),
+            ("1,2,", b','),
+            ("1 x", b' '),
+            ("1\t2", b' '),
+        ] {
+            assert_eq!(split_ints::<i32>(text, sep), None, "{:?}", text);
+        }
+        assert_eq!(split_ints::<u32>("-1", b' '), None);
+        assert_eq!(split_ints::<i8>("127 128", b' '), None);
+    }
+
+    #[test]
+    fn test_extract_ints() {
+        assert_eq!(
+            ints::<i64>("p=-3,14 v=2,-1 range 5-9"),
+            Ok(vec![-3, 14, 2, -1, 5, 9])
+        );
+        assert_eq!(uints::<u32>("p=-3,14 7|53"), Ok(vec![3, 14, 7, 53]));
+        assert_eq!(iter_ints::<i32>("no numbers").count(), 0);
+
+        let err = ints::<u8>("1 300 2").unwrap_err();
+        assert_eq!((err.column, err.span.clone()), (3, 2..5));
+        assert_eq!(err.message, "number '300' out of range for u8");
+    }
+}
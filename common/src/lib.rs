@@ -0,0 +1,17 @@
+//! Shared building blocks for the daily puzzle crates
+
+// Lets code generated by `#[derive(AocParse)]` refer to `::aoc_common` inside this crate too
+extern crate self as aoc_common;
+
+pub mod format;
+pub mod grid;
+pub mod ints;
+pub mod metamorphic;
+pub mod parse;
+pub mod rng;
+pub mod sim;
+
+pub use aoc_derive::AocParse;
+pub use grid::{Grid, GridError};
+pub use parse::{ParseError, ParseMode, Parsed};
+pub use sim::Simulation;
@@ -0,0 +1,228 @@
+//! Metamorphic testing: rewrite an input in a way that must not change its answer,
+//! then check that the solver agrees with itself
+
+use crate::rng::Rng;
+use std::fmt::{self, Debug};
+
+/// A rewrite of a puzzle input that a day declares its answers invariant under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Shuffle all non-empty lines with the given seed
+    ShuffleLines(u64),
+    /// Shuffle the lines within each blank-line separated section, keeping sections in order
+    ShuffleSections(u64),
+    /// Rotate a rectangular grid a quarter turn clockwise
+    Rotate,
+    /// Mirror a rectangular grid left to right
+    Reflect,
+}
+
+/// The seven non-identity symmetries of a grid: three rotations, and a reflection
+/// followed by zero to three rotations
+pub fn grid_symmetries() -> Vec<Vec<Transform>> {
+    (1..8)
+        .map(|index| {
+            let mut transforms = vec![Transform::Reflect; index / 4];
+            transforms.extend(std::iter::repeat_n(Transform::Rotate, index % 4));
+            transforms
+        })
+        .collect()
+}
+
+impl Transform {
+    /// Apply the transform, returning the new input without a trailing newline
+    pub fn apply(self, input: &str) -> String {
+        match self {
+            Transform::ShuffleLines(seed) => {
+                let mut lines: Vec<&str> = input.lines().filter(|l| !l.trim().is_empty()).collect();
+                Rng::new(seed).shuffle(&mut lines);
+                lines.join("\n")
+            }
+            Transform::ShuffleSections(seed) => {
+                let mut rng = Rng::new(seed);
+                split_blank(input)
+                    .into_iter()
+                    .map(|mut section| {
+                        rng.shuffle(&mut section);
+                        section.join("\n")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            }
+            Transform::Rotate => {
+                let grid = grid(input);
+                let rows = grid.first().map_or(0, Vec::len);
+                let cols = grid.len();
+                let rotated: Vec<String> = (0..rows)
+                    .map(|row| (0..cols).map(|col| grid[cols - 1 - col][row]).collect())
+                    .collect();
+                rotated.join("\n")
+            }
+            Transform::Reflect => {
+                let reflected: Vec<String> = grid(input)
+                    .into_iter()
+                    .map(|row| row.into_iter().rev().collect())
+                    .collect();
+                reflected.join("\n")
+            }
+        }
+    }
+}
+
+/// `count` single-transform sequences from a seeded transform, starting at `seed`,
+/// such as four different shuffles of the lines
+pub fn reseeded(transform: fn(u64) -> Transform, seed: u64, count: u64) -> Vec<Vec<Transform>> {
+    (seed..seed.wrapping_add(count))
+        .map(|seed| vec![transform(seed)])
+        .collect()
+}
+
+/// Apply `transforms` in order
+pub fn apply_all(transforms: &[Transform], input: &str) -> String {
+    transforms
+        .iter()
+        .fold(input.to_string(), |input, transform| {
+            transform.apply(&input)
+        })
+}
+
+/// Non-empty lines grouped into blank-line separated sections
+fn split_blank(input: &str) -> Vec<Vec<&str>> {
+    let mut sections = vec![Vec::new()];
+    for line in input.trim().lines() {
+        let last = sections
+            .last_mut()
+            .expect("there is always a current section");
+        if !line.trim().is_empty() {
+            last.push(line);
+        } else if !last.is_empty() {
+            sections.push(Vec::new());
+        }
+    }
+    sections
+}
+
+/// The characters of a rectangular grid, row by row
+fn grid(input: &str) -> Vec<Vec<char>> {
+    let grid: Vec<Vec<char>> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim_end().chars().collect())
+        .collect();
+    assert!(
+        grid.windows(2).all(|pair| pair[0].len() == pair[1].len()),
+        "grid transforms need a rectangular grid"
+    );
+    grid
+}
+
+/// A transformed input whose answer differed from the original's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch<T> {
+    pub transforms: Vec<Transform>,
+    pub expected: T,
+    pub actual: T,
+}
+
+impl<T: Debug> fmt::Display for Mismatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "answer changed from {:?} to {:?} after {:?}",
+            self.expected, self.actual, self.transforms
+        )
+    }
+}
+
+impl<T: Debug> std::error::Error for Mismatch<T> {}
+
+/// Check that `solve` gives the same answer for `input` and for each sequence of `transforms`
+pub fn check<T, F>(input: &str, transforms: &[Vec<Transform>], solve: F) -> Result<(), Mismatch<T>>
+where
+    T: PartialEq,
+    F: Fn(&str) -> T,
+{
+    let expected = solve(input);
+    for sequence in transforms {
+        let actual = solve(&apply_all(sequence, input));
+        if actual != expected {
+            return Err(Mismatch {
+                transforms: sequence.clone(),
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// [`check`] both parts of a puzzle at once
+pub fn check_both<A, B>(
+    part1: fn(&str) -> A,
+    part2: fn(&str) -> B,
+    input: &str,
+    transforms: &[Vec<Transform>],
+) -> Result<(), Mismatch<(A, B)>>
+where
+    A: PartialEq,
+    B: PartialEq,
+{
+    check(input, transforms, |input| (part1(input), part2(input)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_transforms() {
+        let input = "abc\ndef\n";
+        assert_eq!(Transform::Rotate.apply(input), "da\neb\nfc");
+        assert_eq!(Transform::Reflect.apply(input), "cba\nfed");
+        assert_eq!(apply_all(&[Transform::Rotate; 4], input), "abc\ndef");
+
+        let symmetries = grid_symmetries();
+        let mut images: Vec<String> = symmetries.iter().map(|t| apply_all(t, input)).collect();
+        images.push(input.trim().to_string());
+        images.sort();
+        images.dedup();
+        assert_eq!((symmetries.len(), images.len()), (7, 8));
+    }
+
+    #[test]
+    fn test_shuffles_keep_lines_and_sections() {
+        let input = "1|2\n3|4\n5|6\n\n1,2\n3,4\n";
+        let shuffled = Transform::ShuffleSections(3).apply(input);
+        let (rules, updates) = shuffled.split_once("\n\n").unwrap();
+        let mut rules: Vec<&str> = rules.lines().collect();
+        rules.sort();
+        assert_eq!(rules, ["1|2", "3|4", "5|6"]);
+        assert_eq!(updates.lines().count(), 2);
+
+        let mut lines: Vec<String> = Transform::ShuffleLines(3)
+            .apply("a\nb\n\nc")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        assert_eq!(lines, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_check_reports_first_mismatch() {
+        let first_line = |input: &str| input.lines().next().map(str::to_string);
+        assert!(check("ab\ncd", &grid_symmetries(), |input| input.len()).is_ok());
+
+        let lines = |input: &str| input.lines().count();
+        let shuffles = reseeded(Transform::ShuffleLines, 5, 3);
+        assert_eq!(shuffles.len(), 3);
+        assert_eq!(check_both(lines, str::len, "a\nb\nc", &shuffles), Ok(()));
+
+        let mismatch = check("ab\ncd", &[vec![Transform::Reflect]], first_line).unwrap_err();
+        assert_eq!(mismatch.transforms, vec![Transform::Reflect]);
+        assert_eq!(
+            mismatch.to_string(),
+            "answer changed from Some(\"ab\") to Some(\"ba\") after [Reflect]"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Parse errors that point at the offending text of a puzzle input
+
+use std::fmt;
+use std::ops::Range;
+use std::process::ExitCode;
+
+/// Exit status of the runners when the input cannot be parsed, distinct from
+/// ordinary failures (1) and panics
+pub const EXIT_PARSE_ERROR: u8 = 65;
+
+/// How malformed lines are handled while parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail on the first malformed line
+    #[default]
+    Strict,
+    /// Skip malformed lines and count them
+    Lenient,
+}
+
+/// Parsed values together with the number of lines skipped in lenient mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parsed<T> {
+    pub items: Vec<T>,
+    pub skipped: usize,
+}
+
+/// Malformed input, located by line, column and byte span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Line of the start of the span, starting at 1
+    pub line: usize,
+    /// Column of the start of the span in characters, starting at 1
+    pub column: usize,
+    /// Byte range of the offending text within the input
+    pub span: Range<usize>,
+}
+
+/// Byte range of `part` within `input`. `part` must be a slice of `input`,
+/// such as a line from `lines()` or a token from `split_whitespace()`.
+pub fn span_of(input: &str, part: &str) -> Range<usize> {
+    let start = (part.as_ptr() as usize)
+        .checked_sub(input.as_ptr() as usize)
+        .filter(|&start| start + part.len() <= input.len())
+        .expect("part must be a slice of the input");
+    start..start + part.len()
+}
+
+impl ParseError {
+    /// Error covering the bytes `span` of `input`
+    pub fn new(input: &str, span: Range<usize>, message: impl Into<String>) -> Self {
+        let before = &input[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        ParseError {
+            message: message.into(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+        }
+    }
+
+    /// Error covering `part`, which must be a slice of `input`
+    pub fn at(input: &str, part: &str, message: impl Into<String>) -> Self {
+        Self::new(input, span_of(input, part), message)
+    }
+
+    /// Move an error found in a fragment to its place in the full input, given the
+    /// number of lines and bytes that precede the fragment
+    pub fn offset_by(mut self, lines: usize, bytes: usize) -> Self {
+        self.line += lines;
+        self.span = self.span.start + bytes..self.span.end + bytes;
+        self
+    }
+
+    /// Format the error rustc-style, quoting the offending line of `input` with a
+    /// caret under the span. `source` names the input, such as a file path.
+    pub fn render(&self, input: &str, source: &str) -> String {
+        let mut start = self.span.start.min(input.len());
+        while !input.is_char_boundary(start) {
+            start -= 1;
+        }
+        let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+        let end = self.span.end.clamp(start, line_end);
+
+        let text = input[line_start..line_end].trim_end_matches('\r');
+        let indent = input[line_start..start].chars().count();
+        let width = input
+            .get(start..end)
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        format!(
+            "error: {message}\n{gutter}--> {source}:{line}:{column}\n{gutter} |\n{line} | {text}\n{gutter} | {pad}{carets}",
+            message = self.message,
+            line = self.line,
+            column = self.column,
+            pad = " ".repeat(indent),
+            carets = "^".repeat(width),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Print `err` as an annotated snippet on stderr and return the parse-error exit status
+pub fn report(err: &ParseError, input: &str, source: &str) -> ExitCode {
+    eprintln!("{}", err.render(input, source));
+    ExitCode::from(EXIT_PARSE_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_and_render() {
+        let input = "1 2\n3 x5\n";
+        let token = input.lines().nth(1).unwrap().split(' ').nth(1).unwrap();
+        let err = ParseError::at(input, token, "invalid number 'x5'");
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 3, 6..8));
+        assert_eq!(err.to_string(), "line 2, column 3: invalid number 'x5'");
+        assert_eq!(
+            err.render(input, "<stdin>"),
+            "error: invalid number 'x5'\n --> <stdin>:2:3\n  |\n2 | 3 x5\n  |   ^^"
+        );
+    }
+
+    #[test]
+    fn test_offset_and_empty_span() {
+        // Missing text at the end of a line still gets a single caret
+        let fragment = "7";
+        let err = ParseError::at(fragment, &fragment[1..], "expected two columns").offset_by(1, 4);
+        assert_eq!((err.line, err.column, err.span.clone()), (2, 2, 5..5));
+        assert!(err.render("abc\n7\n", "in").ends_with("2 | 7\n  |  ^"));
+    }
+}
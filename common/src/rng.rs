@@ -0,0 +1,90 @@
+//! A small seeded random number generator for building puzzle inputs.
+//! The sequence for a seed never changes, so generated inputs are reproducible.
+
+use std::ops::Range;
+
+/// xorshift64* seeded through SplitMix64, so that nearby seeds give unrelated streams
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        // xorshift gets stuck on zero
+        Rng { state: z.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A value in `range`, which must not be empty
+    pub fn range(&mut self, range: Range<u64>) -> u64 {
+        assert!(!range.is_empty(), "empty range {:?}", range);
+        // Multiply-shift keeps the bias negligible for the small ranges used here
+        let span = range.end - range.start;
+        range.start + ((u128::from(self.next_u64()) * u128::from(span)) >> 64) as u64
+    }
+
+    /// An index below `len`, which must not be zero
+    pub fn index(&mut self, len: usize) -> usize {
+        self.range(0..len as u64) as usize
+    }
+
+    /// True with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < p
+    }
+
+    /// A random element of `items`, which must not be empty
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.index(items.len())]
+    }
+
+    /// Shuffle `items` in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.index(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_per_seed() {
+        let draw = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..5).map(|_| rng.range(0..1000)).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        assert!(draw(0).iter().all(|&n| n < 1000));
+    }
+
+    #[test]
+    fn test_shuffle_and_chance() {
+        let mut rng = Rng::new(1);
+        let mut items: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2000..3000).contains(&hits));
+        assert!(!(0..100).any(|_| rng.chance(0.0)));
+    }
+}
@@ -0,0 +1,17 @@
+//! Step-by-step simulations that can be drawn as a character grid
+
+use crate::Grid;
+
+/// A puzzle state that advances in discrete steps and can be drawn at any point
+pub trait Simulation {
+    /// Advance by one step, returning false once the simulation has finished
+    fn step(&mut self) -> bool;
+
+    /// The current state drawn as a grid of glyphs
+    fn frame(&self) -> Grid<char>;
+
+    /// Cell a viewer should keep in view, such as the position of a moving agent
+    fn focus(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
@@ -0,0 +1,484 @@
+//! `#[derive(AocParse)]`: `FromStr` implementations generated from a line format
+//!
+//! ```ignore
+//! #[derive(AocParse)]
+//! #[aoc(format = "{target}: {operands: sep=' ', min=1}")]
+//! struct Equation {
+//!     target: u64,
+//!     operands: Vec<u64>,
+//! }
+//!
+//! #[derive(AocParse)]
+//! #[aoc(sections)]
+//! struct PrintQueue {
+//!     rules: Vec<Rule>,
+//!     #[aoc(default)]
+//!     updates: Vec<Update>,
+//! }
+//! ```
+//!
+//! A format mixes literal text with `{field}` placeholders; `{{` and `}}` stand for
+//! braces. A placeholder with a `sep` option fills a `Vec` by splitting on that
+//! separator, where `sep=' '` splits on any whitespace, and `min` sets the fewest
+//! values accepted. `label` names one value of the field in error messages, such as
+//! `{operands: sep=' ', label='operand'}`; it defaults to the field name. Whitespace
+//! around literals is flexible.
+//!
+//! With `sections`, the input is split on blank lines and each field, a `Vec` of
+//! a type that itself implements `FromStr<Err = ParseError>`, takes one section
+//! parsed line by line. Trailing fields marked `#[aoc(default)]` may have no
+//! section and are left empty. The generated code relies on the `aoc_common` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields, Ident, LitStr, parse_macro_input};
+
+#[proc_macro_derive(AocParse, attributes(aoc))]
+pub fn derive_aoc_parse(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How the whole input of `from_str` is laid out
+enum Layout {
+    Format(LitStr),
+    Sections,
+}
+
+/// One piece of a line format
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Literal(String),
+    Field {
+        name: String,
+        sep: Option<String>,
+        min: usize,
+        label: Option<String>,
+    },
+}
+
+fn layout(input: &DeriveInput) -> syn::Result<Layout> {
+    let mut layout = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("aoc"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("format") {
+                layout = Some(Layout::Format(meta.value()?.parse()?));
+                Ok(())
+            } else if meta.path.is_ident("sections") {
+                layout = Some(Layout::Sections);
+                Ok(())
+            } else {
+                Err(meta.error("expected `format = \"...\"` or `sections`"))
+            }
+        })?;
+    }
+
+    layout.ok_or_else(|| {
+        syn::Error::new(
+            Span::call_site(),
+            "AocParse needs #[aoc(format = \"...\")] or #[aoc(sections)]",
+        )
+    })
+}
+
+/// Whether a field carries `#[aoc(default)]`, letting its section be missing
+fn is_default(field: &Field) -> syn::Result<bool> {
+    let mut default = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("aoc"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `default`"))
+            }
+        })?;
+    }
+    Ok(default)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let named: Vec<&Field> = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            _ => return Err(syn::Error::new_spanned(name, "AocParse needs named fields")),
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "AocParse only supports structs",
+            ));
+        }
+    };
+
+    let fields: Vec<&Ident> = named.iter().flat_map(|f| &f.ident).collect();
+    let defaults = named
+        .iter()
+        .map(|f| is_default(f))
+        .collect::<syn::Result<Vec<bool>>>()?;
+
+    let body = match layout(input)? {
+        Layout::Format(format) => {
+            if let Some(field) = named
+                .iter()
+                .zip(&defaults)
+                .find_map(|(f, &d)| d.then_some(f))
+            {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`#[aoc(default)]` only applies to sections",
+                ));
+            }
+            format_body(&format, &fields)?
+        }
+        Layout::Sections => sections_body(&named, &defaults)?,
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::std::str::FromStr for #name #ty_generics #where_clause {
+            type Err = ::aoc_common::ParseError;
+
+            fn from_str(input: &str) -> ::std::result::Result<Self, Self::Err> {
+                #body
+            }
+        }
+    })
+}
+
+/// Split a format string into literals and placeholders
+fn parse_format(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err("unclosed `{` in format".to_string()),
+                    }
+                }
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(parse_placeholder(&placeholder)?);
+            }
+            '}' => return Err("unmatched `}` in format; use `}}` for a literal brace".to_string()),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Parse `name` or `name: sep=',', min=1, label='page'`
+fn parse_placeholder(placeholder: &str) -> Result<Piece, String> {
+    let (name, options) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("empty placeholder `{}` in format".to_string());
+    }
+
+    let mut sep = None;
+    let mut min = 0;
+    let mut label = None;
+    let mut rest = options.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key=value` in options of `{}`", name))?;
+        let after = after.trim_start();
+
+        // Values are either quoted with single quotes or run up to the next comma
+        let (value, after) = match after.strip_prefix('\'') {
+            Some(quoted) => {
+                let end = quoted
+                    .find('\'')
+                    .ok_or_else(|| format!("unclosed quote in options of `{}`", name))?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => {
+                let end = after.find(',').unwrap_or(after.len());
+                (after[..end].trim(), &after[end..])
+            }
+        };
+
+        match key.trim() {
+            "sep" if !value.is_empty() => sep = Some(value.to_string()),
+            "sep" => return Err(format!("empty separator for `{}`", name)),
+            "min" => {
+                min = value
+                    .parse()
+                    .map_err(|_| format!("`min` for `{}` must be a number", name))?
+            }
+            "label" if !value.is_empty() => label = Some(value.to_string()),
+            "label" => return Err(format!("empty label for `{}`", name)),
+            key => return Err(format!("unknown option `{}` for `{}`", key, name)),
+        }
+
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+
+    if min > 0 && sep.is_none() {
+        return Err(format!("`min` needs a `sep` for `{}`", name));
+    }
+    Ok(Piece::Field {
+        name,
+        sep,
+        min,
+        label,
+    })
+}
+
+/// Check the pieces against the struct fields and generate the `from_str` body
+fn format_body(format: &LitStr, fields: &[&Ident]) -> syn::Result<TokenStream2> {
+    let error = |message: String| syn::Error::new(format.span(), message);
+    let pieces = parse_format(&format.value()).map_err(error)?;
+
+    for window in pieces.windows(2) {
+        match window {
+            [Piece::Field { name: a, .. }, Piece::Field { name: b, .. }] => {
+                return Err(error(format!(
+                    "fields `{}` and `{}` must be separated by literal text",
+                    a, b
+                )));
+            }
+            [
+                Piece::Field {
+                    name, sep: Some(_), ..
+                },
+                Piece::Literal(text),
+            ] if text.trim().is_empty() => {
+                return Err(error(format!(
+                    "list `{}` must be followed by non-whitespace text or end the line",
+                    name
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    let placeholders: Vec<(&String, &Option<String>, usize, String)> = pieces
+        .iter()
+        .filter_map(|piece| match piece {
+            Piece::Field {
+                name,
+                sep,
+                min,
+                label,
+            } => Some((name, sep, *min, label_of(name, label))),
+            Piece::Literal(_) => None,
+        })
+        .collect();
+
+    for (name, ..) in &placeholders {
+        if !fields.iter().any(|field| field == name) {
+            return Err(error(format!("no field named `{}`", name)));
+        }
+        if placeholders
+            .iter()
+            .filter(|(other, ..)| other == name)
+            .count()
+            > 1
+        {
+            return Err(error(format!("field `{}` appears more than once", name)));
+        }
+    }
+    if let Some(field) = fields
+        .iter()
+        .find(|field| !placeholders.iter().any(|(name, ..)| *field == name))
+    {
+        return Err(error(format!(
+            "field `{}` does not appear in the format",
+            field
+        )));
+    }
+
+    let segments = pieces.iter().map(|piece| match piece {
+        Piece::Literal(text) => quote!(::aoc_common::format::Segment::Literal(#text)),
+        Piece::Field {
+            name,
+            sep: None,
+            label,
+            ..
+        } => {
+            let label = label_of(name, label);
+            quote!(::aoc_common::format::Segment::Value(#label))
+        }
+        Piece::Field {
+            name,
+            sep: Some(_),
+            label,
+            ..
+        } => {
+            let label = label_of(name, label);
+            quote!(::aoc_common::format::Segment::List(#label))
+        }
+    });
+
+    let values = placeholders
+        .iter()
+        .enumerate()
+        .map(|(index, (name, sep, min, label))| {
+            let ident = Ident::new(name, format.span());
+            match sep {
+                None => quote! {
+                    #ident: ::aoc_common::format::parse_value(input, fields[#index], #label)?
+                },
+                Some(sep) => quote! {
+                    #ident: ::aoc_common::format::parse_list(input, fields[#index], #sep, #min, #label)?
+                },
+            }
+        });
+
+    Ok(quote! {
+        const SEGMENTS: &[::aoc_common::format::Segment] = &[#(#segments),*];
+        let fields = ::aoc_common::format::match_line(input, SEGMENTS)?;
+        ::std::result::Result::Ok(Self { #(#values),* })
+    })
+}
+
+/// The name of a field's values in error messages: its label, or its name in words
+fn label_of(name: &str, label: &Option<String>) -> String {
+    label.clone().unwrap_or_else(|| name.replace('_', " "))
+}
+
+/// Generate a `from_str` body that fills each field from one blank-line-separated section.
+/// Trailing `#[aoc(default)]` fields may have no section and are left empty.
+fn sections_body(fields: &[&Field], defaults: &[bool]) -> syn::Result<TokenStream2> {
+    let count = fields.len();
+    let required = defaults.iter().take_while(|&&d| !d).count();
+    if let Some(index) = defaults[required..].iter().position(|&d| !d) {
+        return Err(syn::Error::new_spanned(
+            fields[required + index],
+            "sections after a `#[aoc(default)]` field must also be `#[aoc(default)]`",
+        ));
+    }
+
+    let values = fields.iter().enumerate().map(|(index, field)| {
+        let field = &field.ident;
+        quote! {
+            #field: match sections.get(#index) {
+                ::std::option::Option::Some(section) => section.parse_lines()?,
+                ::std::option::Option::None => ::std::default::Default::default(),
+            }
+        }
+    });
+
+    Ok(quote! {
+        let sections = ::aoc_common::format::split_sections(input, #required, #count)?;
+        ::std::result::Result::Ok(Self { #(#values),* })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            parse_format("{target}: {operands: sep=' ', min=1}"),
+            Ok(vec![
+                Piece::Field {
+                    name: "target".to_string(),
+                    sep: None,
+                    min: 0,
+                    label: None
+                },
+                Piece::Literal(": ".to_string()),
+                Piece::Field {
+                    name: "operands".to_string(),
+                    sep: Some(" ".to_string()),
+                    min: 1,
+                    label: None
+                },
+            ])
+        );
+        assert_eq!(
+            parse_format("{{{pages: sep=',', label='page number'}}}"),
+            Ok(vec![
+                Piece::Literal("{".to_string()),
+                Piece::Field {
+                    name: "pages".to_string(),
+                    sep: Some(",".to_string()),
+                    min: 0,
+                    label: Some("page number".to_string())
+                },
+                Piece::Literal("}".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(parse_format("{a").is_err());
+        assert!(parse_format("a}").is_err());
+        assert!(parse_format("{}").is_err());
+        assert_eq!(
+            parse_format("{a: size=3}"),
+            Err("unknown option `size` for `a`".to_string())
+        );
+        assert_eq!(
+            parse_format("{a: min=2}"),
+            Err("`min` needs a `sep` for `a`".to_string())
+        );
+        assert_eq!(
+            parse_format("{a: label=''}"),
+            Err("empty label for `a`".to_string())
+        );
+        assert_eq!(label_of("page_number", &None), "page number");
+    }
+
+    #[test]
+    fn test_default_sections() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[aoc(sections)]
+            struct Queue { #[aoc(default)] rules: Vec<Rule>, updates: Vec<Update> }
+        };
+        let err = expand(&input).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("sections after a `#[aoc(default)]`")
+        );
+
+        let input: DeriveInput = syn::parse_quote! {
+            #[aoc(format = "{a}")]
+            struct Line { #[aoc(default)] a: u32 }
+        };
+        let err = expand(&input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`#[aoc(default)]` only applies to sections"
+        );
+    }
+}